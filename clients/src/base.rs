@@ -1,82 +1,523 @@
 use log;
 use reqwest;
+use serde::Serialize;
 use serde_json;
-use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::Error;
+
+/// Generic HTTP client the service clients are built on. Clones share their
+/// default headers, so a header replaced through one clone (e.g. a refreshed
+/// access token) is used by all of them.
+/// Parses a header value, such as a token read from the environment.
+pub(crate) fn header_value(value: &str) -> Result<reqwest::header::HeaderValue, Error> {
+    value
+        .parse()
+        .map_err(|e: reqwest::header::InvalidHeaderValue| Error::InvalidHeader { source: e.into() })
+}
+
+#[derive(Clone)]
 pub struct HttpClient {
     base_url: String,
-    headers: reqwest::header::HeaderMap,
+    headers: Arc<RwLock<reqwest::header::HeaderMap>>,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
     pub client: reqwest::Client,
 }
 
 impl HttpClient {
-    pub fn new(
-        base_url: &str,
-        headers: Option<reqwest::header::HeaderMap>,
-    ) -> Result<Self, reqwest::Error> {
+    pub fn new(base_url: &str, headers: Option<reqwest::header::HeaderMap>) -> Result<Self, Error> {
         let mut base_headers = reqwest::header::HeaderMap::new();
         base_headers.insert(reqwest::header::ACCEPT, "application/json".parse().unwrap());
-        base_headers.insert(
-            reqwest::header::CONTENT_TYPE,
-            "application/json".parse().unwrap(),
-        );
-        if !headers.is_none() {
-            base_headers.extend(headers.unwrap());
+        if let Some(headers) = headers {
+            base_headers.extend(headers);
         }
         Ok(Self {
-            client: reqwest::Client::builder().build()?,
+            client: reqwest::Client::builder()
+                .build()
+                .map_err(|source| Error::Transport {
+                    url: base_url.to_owned(),
+                    source,
+                })?,
             base_url: base_url.to_owned(),
-            headers: base_headers,
+            headers: Arc::new(RwLock::new(base_headers)),
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
         })
     }
 
-    pub async fn request<T>(
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Throttles every request sent by this client, and its clones, through
+    /// the given limiter.
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Replaces a default header for every subsequent request.
+    pub fn set_header(
+        &self,
+        name: reqwest::header::HeaderName,
+        value: reqwest::header::HeaderValue,
+    ) {
+        self.headers.write().unwrap().insert(name, value);
+    }
+
+    pub fn request(&self, method: reqwest::Method, path: impl Into<String>) -> RequestBuilder<'_> {
+        RequestBuilder {
+            client: self,
+            method,
+            path: path.into(),
+            query: Vec::new(),
+            body: Body::Empty,
+            headers: reqwest::header::HeaderMap::new(),
+            timeout: None,
+            error: None,
+        }
+    }
+
+    pub fn get(&self, path: impl Into<String>) -> RequestBuilder<'_> {
+        self.request(reqwest::Method::GET, path)
+    }
+
+    pub fn post(&self, path: impl Into<String>) -> RequestBuilder<'_> {
+        self.request(reqwest::Method::POST, path)
+    }
+
+    pub fn put(&self, path: impl Into<String>) -> RequestBuilder<'_> {
+        self.request(reqwest::Method::PUT, path)
+    }
+
+    pub fn patch(&self, path: impl Into<String>) -> RequestBuilder<'_> {
+        self.request(reqwest::Method::PATCH, path)
+    }
+
+    pub fn delete(&self, path: impl Into<String>) -> RequestBuilder<'_> {
+        self.request(reqwest::Method::DELETE, path)
+    }
+
+    /// Sends the request, retrying transport failures and retryable status
+    /// codes according to the client's `RetryPolicy`.
+    async fn execute(&self, request: reqwest::Request) -> Result<reqwest::Response, Error> {
+        let method = request.method().clone();
+        let url = request.url().to_string();
+        let retryable = self.retry.retries_method(&method);
+
+        let mut attempt = 1;
+        loop {
+            let can_retry = retryable && attempt < self.retry.max_attempts;
+            let current = match request.try_clone() {
+                Some(current) if can_retry => current,
+                _ => {
+                    return self
+                        .send_once(request)
+                        .await
+                        .map_err(|source| Error::Transport { url, source })
+                }
+            };
+
+            let delay = match self.send_once(current).await {
+                Ok(response) if self.retry.retries_status(response.status()) => {
+                    log::warn!(
+                        "{method} {url} - {} on attempt {attempt}",
+                        response.status()
+                    );
+                    match self.retry.delay(attempt, response.headers()) {
+                        Some(delay) => delay,
+                        None => {
+                            log::warn!("{method} {url} - asked to wait too long, not retrying");
+                            return Ok(response);
+                        }
+                    }
+                }
+                Ok(response) => return Ok(response),
+                Err(source) if RetryPolicy::retries_error(&source) => {
+                    log::warn!("{method} {url} - {source} on attempt {attempt}");
+                    self.retry.backoff(attempt)
+                }
+                Err(source) => return Err(Error::Transport { url, source }),
+            };
+            log::debug!("Retrying {method} {url} in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_once(
         &self,
-        method: reqwest::Method,
-        path: &str,
-        params: Option<HashMap<String, String>>,
-        json: Option<HashMap<String, String>>,
-        data: Option<HashMap<String, String>>,
-    ) -> Result<T, reqwest::Error>
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.acquire().await;
+        }
+        let response = self.client.execute(request).await?;
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.observe(response.status(), response.headers());
+        }
+        Ok(response)
+    }
+}
+
+/// A request being prepared by `HttpClient`. Errors raised while adding
+/// parts (e.g. a body that fails to serialize) are reported by `send`.
+#[must_use = "a request does nothing until it is sent"]
+pub struct RequestBuilder<'a> {
+    client: &'a HttpClient,
+    method: reqwest::Method,
+    path: String,
+    query: Vec<Query>,
+    body: Body,
+    headers: reqwest::header::HeaderMap,
+    timeout: Option<Duration>,
+    error: Option<Error>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    /// Appends the serialized value to the query string.
+    pub fn query<Q>(mut self, query: &Q) -> Self
+    where
+        Q: Serialize + ?Sized,
+    {
+        match Query::new(query) {
+            Ok(query) => self.query.push(query),
+            Err(e) => self.error = self.error.or(Some(e)),
+        }
+        self
+    }
+
+    pub fn json<B>(self, body: &B) -> Self
+    where
+        B: Serialize + ?Sized,
+    {
+        let body = Body::json(body);
+        self.with_body(body)
+    }
+
+    pub fn form<B>(self, body: &B) -> Self
+    where
+        B: Serialize + ?Sized,
+    {
+        let body = Body::form(body);
+        self.with_body(body)
+    }
+
+    pub fn body(mut self, body: Body) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Adds a header to this request only, overriding the client's default.
+    pub fn header<K, V>(mut self, name: K, value: V) -> Self
+    where
+        K: TryInto<reqwest::header::HeaderName>,
+        K::Error: Into<http::Error>,
+        V: TryInto<reqwest::header::HeaderValue>,
+        V::Error: Into<http::Error>,
+    {
+        let header = name
+            .try_into()
+            .map_err(Into::into)
+            .and_then(|name| Ok((name, value.try_into().map_err(Into::into)?)));
+        match header {
+            Ok((name, value)) => {
+                self.headers.insert(name, value);
+            }
+            Err(source) => self.error = self.error.or(Some(Error::InvalidHeader { source })),
+        }
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sends the request and decodes the JSON response into `T`. Empty bodies
+    /// decode into `NoContent`, `()` or `Option<_>`.
+    pub async fn send<T>(self) -> Result<T, Error>
+    where
+        T: for<'de> serde::Deserialize<'de>,
+    {
+        Ok(self.send_response::<T>().await?.body)
+    }
+
+    /// Like `send`, but also returns the status, headers and timing of the
+    /// response.
+    pub async fn send_response<T>(self) -> Result<Response<T>, Error>
     where
         T: for<'de> serde::Deserialize<'de>,
     {
-        let url_str = format!("{}{}", self.base_url, path);
-        let params_ = params.unwrap_or(HashMap::new());
-        let url = if params_.is_empty() {
-            reqwest::Url::parse(&url_str).unwrap()
-        } else {
-            reqwest::Url::parse_with_params(&url_str, params_).unwrap()
-        };
-
-        let builder: reqwest::RequestBuilder = if data.is_some() {
-            let form_data = data.unwrap();
-            self.client
-                .request(method.clone(), url.clone())
-                .headers(self.headers.clone())
-                .form(&form_data)
-        } else {
-            let payload: HashMap<String, String> = json.unwrap_or(HashMap::new());
-            self.client
-                .request(method.clone(), url.clone())
-                .headers(self.headers.clone())
-                .json(&payload)
-        };
-
-        let response = builder.send().await?;
-
-        log::debug!(
-            "{method} {url} - {code}",
-            url = url,
-            code = response.status(),
-        );
-
-        let mut text = response.text().await?;
-        if text.is_empty() {
-            text.push_str("{}")
+        let response = self.receive_text().await?;
+        let body = decode(response.url.as_str(), response.body)?;
+        Ok(Response {
+            status: response.status,
+            headers: response.headers,
+            url: response.url,
+            elapsed: response.elapsed,
+            body,
+        })
+    }
+
+    /// Sends the request and returns the response body as text.
+    pub async fn send_text(self) -> Result<String, Error> {
+        Ok(self.receive_text().await?.body)
+    }
+
+    /// Sends the request and returns the raw response body.
+    pub async fn send_bytes(self) -> Result<Vec<u8>, Error> {
+        let response = self.dispatch().await?;
+        let url = response.url().to_string();
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|source| Error::Transport { url, source })?;
+        Ok(bytes.to_vec())
+    }
+
+    fn with_body(mut self, body: Result<Body, Error>) -> Self {
+        match body {
+            Ok(body) => self.body = body,
+            Err(e) => self.error = self.error.or(Some(e)),
+        }
+        self
+    }
+
+    async fn receive_text(self) -> Result<Response<String>, Error> {
+        let started = Instant::now();
+        let method = self.method.clone();
+        let response = self.dispatch().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let url = response.url().clone();
+        let text = response.text().await.map_err(|source| Error::Transport {
+            url: url.to_string(),
+            source,
+        })?;
+        // Bodies are not logged: some, like Trakt's token responses, hold
+        // credentials.
+        log::debug!("{method} {url} -- {} bytes", text.len());
+        Ok(Response {
+            status,
+            headers,
+            url,
+            elapsed: started.elapsed(),
+            body: text,
+        })
+    }
+
+    /// Builds and sends the request, turning non-success statuses into errors.
+    async fn dispatch(self) -> Result<reqwest::Response, Error> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        let mut url_str = format!("{}{}", self.client.base_url, self.path);
+        let query = self
+            .query
+            .iter()
+            .map(|query| query.0.as_str())
+            .filter(|query| !query.is_empty())
+            .collect::<Vec<_>>()
+            .join("&");
+        if !query.is_empty() {
+            url_str.push(if url_str.contains('?') { '&' } else { '?' });
+            url_str.push_str(&query);
         }
-        log::debug!("{method} {url} -- {text}", url = url, text = text);
-        let resp = serde_json::from_str(&text).unwrap();
-        Ok(resp)
+        let url = reqwest::Url::parse(&url_str).map_err(|source| Error::InvalidUrl {
+            url: url_str.clone(),
+            source,
+        })?;
+
+        let mut builder = self
+            .body
+            .apply(
+                self.client
+                    .client
+                    .request(self.method.clone(), url.clone())
+                    .headers(self.client.headers.read().unwrap().clone()),
+            )
+            .headers(self.headers);
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let request = builder.build().map_err(|source| Error::Transport {
+            url: url.to_string(),
+            source,
+        })?;
+
+        let response = self.client.execute(request).await?;
+        let status = response.status();
+        log::debug!("{method} {url} - {status}", method = self.method);
+
+        if !status.is_success() {
+            let text = response.text().await.map_err(|source| Error::Transport {
+                url: url.to_string(),
+                source,
+            })?;
+            log::debug!("{method} {url} -- {text}", method = self.method);
+            return Err(Error::from_status(url.to_string(), status, text));
+        }
+        Ok(response)
+    }
+}
+
+/// A decoded response body together with the response metadata.
+#[derive(Debug)]
+pub struct Response<T> {
+    pub status: reqwest::StatusCode,
+    pub headers: reqwest::header::HeaderMap,
+    pub url: reqwest::Url,
+    /// Time from sending the request, including retries and rate limiting,
+    /// until the body was read.
+    pub elapsed: Duration,
+    pub body: T,
+}
+
+impl<T> Response<T> {
+    /// The value of a header, if present and valid UTF-8.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+
+    /// The value of a header parsed into `V`, e.g. a pagination count.
+    pub fn header_as<V: std::str::FromStr>(&self, name: &str) -> Option<V> {
+        self.header(name)?.parse().ok()
+    }
+
+    pub fn etag(&self) -> Option<&str> {
+        self.header(reqwest::header::ETAG.as_str())
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.header(reqwest::header::LOCATION.as_str())
     }
 }
+
+/// URL-encoded query string built from any `Serialize` value, such as a
+/// struct, a map or a slice of key/value pairs.
+#[derive(Debug, Clone, Default)]
+struct Query(String);
+
+impl Query {
+    fn new<Q>(query: &Q) -> Result<Self, Error>
+    where
+        Q: Serialize + ?Sized,
+    {
+        serde_urlencoded::to_string(query)
+            .map(Query)
+            .map_err(|source| Error::Encode {
+                target: std::any::type_name::<Q>(),
+                source: Box::new(source),
+            })
+    }
+}
+
+/// Body of an outgoing request.
+#[derive(Debug, Clone, Default)]
+pub enum Body {
+    #[default]
+    Empty,
+    Json(Vec<u8>),
+    Form(String),
+    Bytes {
+        content_type: String,
+        bytes: Vec<u8>,
+    },
+}
+
+impl Body {
+    /// Serializes any `Serialize` value, including nested structures, as JSON.
+    pub fn json<B>(body: &B) -> Result<Self, Error>
+    where
+        B: Serialize + ?Sized,
+    {
+        serde_json::to_vec(body)
+            .map(Body::Json)
+            .map_err(|source| Error::Encode {
+                target: std::any::type_name::<B>(),
+                source: Box::new(source),
+            })
+    }
+
+    /// Serializes a flat `Serialize` value as `application/x-www-form-urlencoded`.
+    pub fn form<B>(body: &B) -> Result<Self, Error>
+    where
+        B: Serialize + ?Sized,
+    {
+        serde_urlencoded::to_string(body)
+            .map(Body::Form)
+            .map_err(|source| Error::Encode {
+                target: std::any::type_name::<B>(),
+                source: Box::new(source),
+            })
+    }
+
+    pub fn bytes(content_type: &str, bytes: impl Into<Vec<u8>>) -> Self {
+        Body::Bytes {
+            content_type: content_type.to_owned(),
+            bytes: bytes.into(),
+        }
+    }
+
+    fn apply(self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self {
+            Body::Empty => builder,
+            Body::Json(bytes) => builder
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(bytes),
+            Body::Form(form) => builder
+                .header(
+                    reqwest::header::CONTENT_TYPE,
+                    "application/x-www-form-urlencoded",
+                )
+                .body(form),
+            Body::Bytes {
+                content_type,
+                bytes,
+            } => builder
+                .header(reqwest::header::CONTENT_TYPE, content_type)
+                .body(bytes),
+        }
+    }
+}
+
+/// Unit-like response type for endpoints that answer with `204 No Content`
+/// or whose body is irrelevant to the caller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NoContent;
+
+impl<'de> serde::Deserialize<'de> for NoContent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        Ok(NoContent)
+    }
+}
+
+fn decode<T>(url: &str, text: String) -> Result<T, Error>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    // Empty bodies are decoded as `null`, which `NoContent`, `()` and
+    // `Option<_>` all accept.
+    let body = if text.trim().is_empty() {
+        "null"
+    } else {
+        &text
+    };
+    serde_json::from_str(body).map_err(|source| Error::Decode {
+        url: url.to_owned(),
+        target: std::any::type_name::<T>(),
+        text,
+        source,
+    })
+}
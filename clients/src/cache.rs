@@ -0,0 +1,359 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Kinds of cached entries, each with its own lifetime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheKind {
+    /// Ids of a title at one provider mapped to its ids at the others.
+    IdMapping,
+    /// Lookups that found nothing.
+    NotFound,
+    /// Descriptive data such as seasons, episodes and release dates.
+    Metadata,
+}
+
+impl CacheKind {
+    pub const ALL: [CacheKind; 3] = [
+        CacheKind::IdMapping,
+        CacheKind::NotFound,
+        CacheKind::Metadata,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheKind::IdMapping => "id-mapping",
+            CacheKind::NotFound => "not-found",
+            CacheKind::Metadata => "metadata",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<CacheKind> {
+        CacheKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == name)
+    }
+
+    fn default_ttl(&self) -> Duration {
+        match self {
+            CacheKind::IdMapping => Duration::from_secs(180 * 24 * 60 * 60),
+            CacheKind::NotFound => Duration::from_secs(60 * 60),
+            CacheKind::Metadata => Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError {
+    #[error("cache database error")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("cached value could not be (de)serialized")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Number of entries of one kind.
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub kind: CacheKind,
+    pub entries: u64,
+    /// Entries past their lifetime, not yet purged.
+    pub expired: u64,
+}
+
+/// A JSON key-value cache in an SQLite file, kept across restarts and shared
+/// by every metadata provider. Lifetimes are checked on read, so changing a
+/// kind's TTL applies to entries stored before.
+#[derive(Clone)]
+pub struct MetadataCache {
+    conn: Arc<Mutex<Connection>>,
+    ttls: HashMap<CacheKind, Duration>,
+}
+
+impl MetadataCache {
+    /// Opens the cache at `path`, creating it if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, CacheError> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A cache that lives only as long as the process.
+    pub fn in_memory() -> Result<Self, CacheError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, CacheError> {
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS entries (
+                kind TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                stored_at INTEGER NOT NULL,
+                PRIMARY KEY (kind, key)
+            )",
+        )?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            ttls: CacheKind::ALL
+                .into_iter()
+                .map(|kind| (kind, kind.default_ttl()))
+                .collect(),
+        })
+    }
+
+    pub fn with_ttl(mut self, kind: CacheKind, ttl: Duration) -> Self {
+        self.ttls.insert(kind, ttl);
+        self
+    }
+
+    /// The value stored under `key`, or `None` if there is none or it expired.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        kind: CacheKind,
+        key: &str,
+    ) -> Result<Option<T>, CacheError> {
+        self.get_stored_after(kind, key, self.expired_before(kind))
+    }
+
+    /// Like `get`, but also treats entries older than `max_age` as expired,
+    /// for values that go stale sooner than the rest of their kind.
+    pub fn get_fresh<T: DeserializeOwned>(
+        &self,
+        kind: CacheKind,
+        key: &str,
+        max_age: Duration,
+    ) -> Result<Option<T>, CacheError> {
+        let cutoff = self
+            .expired_before(kind)
+            .max(now().saturating_sub(secs(max_age)));
+        self.get_stored_after(kind, key, cutoff)
+    }
+
+    fn get_stored_after<T: DeserializeOwned>(
+        &self,
+        kind: CacheKind,
+        key: &str,
+        cutoff: i64,
+    ) -> Result<Option<T>, CacheError> {
+        let value: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT value FROM entries WHERE kind = ?1 AND key = ?2 AND stored_at > ?3",
+                params![kind.as_str(), key, cutoff],
+                |row| row.get(0),
+            )
+            .optional()?;
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn put<T: Serialize>(
+        &self,
+        kind: CacheKind,
+        key: &str,
+        value: &T,
+    ) -> Result<(), CacheError> {
+        let value = serde_json::to_string(value)?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO entries (kind, key, value, stored_at) VALUES (?1, ?2, ?3, ?4)",
+            params![kind.as_str(), key, value, now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> Result<Vec<CacheStats>, CacheError> {
+        let conn = self.conn();
+        CacheKind::ALL
+            .into_iter()
+            .map(|kind| {
+                let (entries, expired) = conn.query_row(
+                    "SELECT COUNT(*), COUNT(*) FILTER (WHERE stored_at <= ?2)
+                     FROM entries WHERE kind = ?1",
+                    params![kind.as_str(), self.expired_before(kind)],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )?;
+                Ok(CacheStats {
+                    kind,
+                    entries,
+                    expired,
+                })
+            })
+            .collect()
+    }
+
+    /// Removes the entries of `kind`, or of every kind if `None`. With
+    /// `expired_only` entries still in their lifetime are kept. Returns the
+    /// number of entries removed.
+    pub fn purge(&self, kind: Option<CacheKind>, expired_only: bool) -> Result<u64, CacheError> {
+        let kinds = match kind {
+            Some(kind) => vec![kind],
+            None => CacheKind::ALL.to_vec(),
+        };
+        let conn = self.conn();
+        let mut removed = 0;
+        for kind in kinds {
+            let before = if expired_only {
+                self.expired_before(kind)
+            } else {
+                i64::MAX
+            };
+            removed += conn.execute(
+                "DELETE FROM entries WHERE kind = ?1 AND stored_at <= ?2",
+                params![kind.as_str(), before],
+            )? as u64;
+        }
+        Ok(removed)
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        // A panic while holding the lock leaves the connection usable.
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Entries of `kind` stored at or before this timestamp have expired.
+    fn expired_before(&self, kind: CacheKind) -> i64 {
+        now().saturating_sub(secs(self.ttls[&kind]))
+    }
+}
+
+fn secs(duration: Duration) -> i64 {
+    duration.as_secs().min(i64::MAX as u64) as i64
+}
+
+/// Current Unix timestamp in seconds.
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_returns_stored_value() {
+        let cache = MetadataCache::in_memory().unwrap();
+        cache
+            .put(CacheKind::Metadata, "movie", &vec![1, 2])
+            .unwrap();
+        let value: Option<Vec<u32>> = cache.get(CacheKind::Metadata, "movie").unwrap();
+        assert_eq!(value, Some(vec![1, 2]));
+        let other: Option<Vec<u32>> = cache.get(CacheKind::IdMapping, "movie").unwrap();
+        assert_eq!(other, None);
+    }
+
+    #[test]
+    fn get_after_expiry_returns_nothing() {
+        // Entries are stored with second precision, so a zero TTL expires
+        // them right away.
+        let cache = MetadataCache::in_memory()
+            .unwrap()
+            .with_ttl(CacheKind::Metadata, Duration::ZERO);
+        cache.put(CacheKind::Metadata, "movie", &1).unwrap();
+        assert_eq!(
+            cache.get::<u32>(CacheKind::Metadata, "movie").unwrap(),
+            None
+        );
+        assert_eq!(
+            cache
+                .get_fresh::<u32>(CacheKind::Metadata, "movie", Duration::from_secs(60))
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn get_fresh_applies_the_shorter_max_age() {
+        let cache = MetadataCache::in_memory().unwrap();
+        cache.put(CacheKind::Metadata, "seasons", &1).unwrap();
+        assert_eq!(
+            cache
+                .get_fresh::<u32>(CacheKind::Metadata, "seasons", Duration::ZERO)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            cache
+                .get_fresh::<u32>(CacheKind::Metadata, "seasons", Duration::from_secs(60))
+                .unwrap(),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn ttl_overrides_apply_per_kind() {
+        let cache = MetadataCache::in_memory()
+            .unwrap()
+            .with_ttl(CacheKind::NotFound, Duration::ZERO);
+        for kind in CacheKind::ALL {
+            cache.put(kind, "key", &kind.as_str()).unwrap();
+        }
+        assert_eq!(
+            cache.get::<String>(CacheKind::NotFound, "key").unwrap(),
+            None
+        );
+        assert!(cache
+            .get::<String>(CacheKind::IdMapping, "key")
+            .unwrap()
+            .is_some());
+        assert!(cache
+            .get::<String>(CacheKind::Metadata, "key")
+            .unwrap()
+            .is_some());
+
+        // Lifetimes are checked on read, so the override also covers entries
+        // stored before it was set.
+        let cache = cache.with_ttl(CacheKind::Metadata, Duration::ZERO);
+        assert_eq!(
+            cache.get::<String>(CacheKind::Metadata, "key").unwrap(),
+            None
+        );
+
+        let expired: Vec<(CacheKind, u64, u64)> = cache
+            .stats()
+            .unwrap()
+            .into_iter()
+            .map(|stats| (stats.kind, stats.entries, stats.expired))
+            .collect();
+        assert_eq!(
+            expired,
+            [
+                (CacheKind::IdMapping, 1, 0),
+                (CacheKind::NotFound, 1, 1),
+                (CacheKind::Metadata, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn purge_counts_removed_entries() {
+        let cache = MetadataCache::in_memory()
+            .unwrap()
+            .with_ttl(CacheKind::NotFound, Duration::ZERO);
+        for key in ["a", "b", "c"] {
+            cache.put(CacheKind::NotFound, key, &key).unwrap();
+            cache.put(CacheKind::Metadata, key, &key).unwrap();
+        }
+        cache.put(CacheKind::IdMapping, "a", &"a").unwrap();
+
+        assert_eq!(cache.purge(None, true).unwrap(), 3);
+        assert_eq!(cache.purge(Some(CacheKind::Metadata), true).unwrap(), 0);
+        assert_eq!(cache.purge(Some(CacheKind::Metadata), false).unwrap(), 3);
+        assert_eq!(cache.purge(None, false).unwrap(), 1);
+        assert_eq!(cache.purge(None, false).unwrap(), 0);
+    }
+
+    #[test]
+    fn kinds_parse_from_their_names() {
+        for kind in CacheKind::ALL {
+            assert_eq!(CacheKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(CacheKind::parse("unknown"), None);
+    }
+}
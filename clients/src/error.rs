@@ -0,0 +1,100 @@
+/// Errors returned by every client in this crate.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The request could not be sent or the response could not be read,
+    /// e.g. the service is down or the connection was reset.
+    #[error("request to {url} failed")]
+    Transport {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// The service answered with a non-success status code. `payload` holds
+    /// the body parsed as JSON when the service sent a structured error.
+    #[error("{url} returned {status}")]
+    Status {
+        url: String,
+        status: reqwest::StatusCode,
+        body: String,
+        payload: Option<serde_json::Value>,
+    },
+    /// The service rejected the credentials the client was built with.
+    #[error("authentication against {url} failed with {status}")]
+    Auth {
+        url: String,
+        status: reqwest::StatusCode,
+        body: String,
+        payload: Option<serde_json::Value>,
+    },
+    /// The response body did not match the expected shape.
+    #[error("failed to decode {target} from {url}")]
+    Decode {
+        url: String,
+        target: &'static str,
+        text: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The request body or query could not be serialized.
+    #[error("failed to encode {target}")]
+    Encode {
+        target: &'static str,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// A per-request header had an invalid name or value.
+    #[error("invalid header")]
+    InvalidHeader {
+        #[source]
+        source: http::Error,
+    },
+    /// The request URL could not be built from the base URL and path.
+    #[error("invalid URL {url}")]
+    InvalidUrl {
+        url: String,
+        #[source]
+        source: url::ParseError,
+    },
+}
+
+impl Error {
+    /// Builds the error for a non-success response.
+    pub(crate) fn from_status(url: String, status: reqwest::StatusCode, body: String) -> Self {
+        let payload = serde_json::from_str(&body).ok();
+        match status {
+            reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => Error::Auth {
+                url,
+                status,
+                body,
+                payload,
+            },
+            _ => Error::Status {
+                url,
+                status,
+                body,
+                payload,
+            },
+        }
+    }
+
+    /// The HTTP status the service answered with, if it answered at all.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Error::Status { status, .. } | Error::Auth { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// The human-readable message from the service's error payload. Covers
+    /// the `message` field used by Jellyfin and Seerr as well as the `error`
+    /// and `error_description` fields used by Trakt and Real-Debrid.
+    pub fn provider_message(&self) -> Option<&str> {
+        let payload = match self {
+            Error::Status { payload, .. } | Error::Auth { payload, .. } => payload.as_ref()?,
+            _ => return None,
+        };
+        ["message", "error_description", "error"]
+            .iter()
+            .find_map(|key| payload.get(key)?.as_str())
+    }
+}
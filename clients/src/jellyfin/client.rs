@@ -1,31 +1,93 @@
-use crate::base::HttpClient;
-use crate::jellyfin::structs::{NoContent, SystemInfo};
+use futures::{Stream, TryStreamExt};
 
+use crate::base::{header_value, HttpClient, NoContent};
+use crate::jellyfin::structs::{BaseItem, ItemQuery, ItemsResult, SessionInfo, SystemInfo};
+use crate::pagination::{offset_paginated, Page};
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::Error;
+
+const PAGE_SIZE: u64 = 200;
+
+#[derive(Clone)]
 pub struct JellyfinClient {
     client: HttpClient,
 }
 
 impl JellyfinClient {
-    pub fn new(base_url: &str, token: &str) -> Self {
+    pub fn new(base_url: &str, token: &str) -> Result<Self, Error> {
         let mut headers = reqwest::header::HeaderMap::new();
         headers.insert(
             reqwest::header::AUTHORIZATION,
-            format!("MediaBrowser Token={token}").parse().unwrap(),
+            header_value(&format!("MediaBrowser Token={token}"))?,
         );
-        Self {
-            client: HttpClient::new(base_url, Some(headers)).unwrap(),
-        }
+        Ok(Self {
+            client: HttpClient::new(base_url, Some(headers))?,
+        })
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.with_retry_policy(policy);
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.client = self.client.with_rate_limiter(limiter);
+        self
+    }
+
+    pub async fn get_system_info(&self) -> Result<SystemInfo, Error> {
+        self.client.get("/System/Info").send::<SystemInfo>().await
     }
 
-    pub async fn get_system_info(&self) -> Result<SystemInfo, reqwest::Error> {
+    pub async fn refresh_libraries(&self) -> Result<NoContent, Error> {
         self.client
-            .request::<SystemInfo>(reqwest::Method::GET, "/System/Info", None, None, None)
+            .post("/Library/Refresh")
+            .send::<NoContent>()
             .await
     }
 
-    pub async fn refresh_libraries(&self) -> Result<NoContent, reqwest::Error> {
+    /// Sessions active in the last `active_within` seconds, including what
+    /// they are playing.
+    pub async fn get_sessions(&self, active_within: u64) -> Result<Vec<SessionInfo>, Error> {
         self.client
-            .request::<NoContent>(reqwest::Method::POST, "/Library/Refresh", None, None, None)
+            .get("/Sessions")
+            .query(&[("activeWithinSeconds", active_within)])
+            .send::<Vec<SessionInfo>>()
             .await
     }
+
+    /// Looks up a single item by id.
+    pub async fn get_item(&self, id: &str) -> Result<BaseItem, Error> {
+        self.client
+            .get(format!("/Items/{id}"))
+            .send::<BaseItem>()
+            .await
+    }
+
+    /// Streams every library item matching `query`, fetching further pages
+    /// as the stream is consumed.
+    pub fn items<'a>(
+        &'a self,
+        query: &'a ItemQuery,
+    ) -> impl Stream<Item = Result<BaseItem, Error>> + 'a {
+        offset_paginated(PAGE_SIZE, move |start_index, limit| async move {
+            let result = self
+                .client
+                .get("/Items")
+                .query(query)
+                .query(&[("StartIndex", start_index), ("Limit", limit)])
+                .send::<ItemsResult>()
+                .await?;
+            Ok(Page {
+                items: result.items,
+                total: Some(result.total_record_count),
+                page_count: None,
+            })
+        })
+    }
+
+    pub async fn get_items(&self, query: &ItemQuery) -> Result<Vec<BaseItem>, Error> {
+        self.items(query).try_collect().await
+    }
 }
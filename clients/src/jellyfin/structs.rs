@@ -1,7 +1,8 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
-#[derive(Deserialize, Debug, Serialize)]
-pub struct NoContent {}
+use crate::media::MediaIds;
 
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "PascalCase")]
@@ -30,3 +31,103 @@ pub struct SystemInfo {
     pub web_path: String,
     pub web_socket_port_number: u16,
 }
+
+/// Filters for `JellyfinClient::items`. Unset fields are left out of the query.
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemQuery {
+    pub recursive: Option<bool>,
+    pub include_item_types: Option<String>,
+    pub fields: Option<String>,
+    pub parent_id: Option<String>,
+    pub any_provider_id_equals: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BaseItem {
+    pub id: String,
+    pub name: String,
+    pub r#type: String,
+    pub production_year: Option<u32>,
+    #[serde(default)]
+    pub provider_ids: HashMap<String, String>,
+    pub series_name: Option<String>,
+    pub parent_index_number: Option<u32>,
+    pub index_number: Option<u32>,
+    /// Frame size of video items.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Duration in ticks of 100ns.
+    pub run_time_ticks: Option<u64>,
+    pub series_id: Option<String>,
+    /// `VideoFile`, or the disc format such as `BluRay` or `Dvd` for rips
+    /// kept as disc images or folders.
+    pub video_type: Option<String>,
+    /// Only present when `MediaStreams` is among the requested fields.
+    #[serde(default)]
+    pub media_streams: Vec<MediaStream>,
+}
+
+impl BaseItem {
+    /// The external ids Jellyfin matched the item to.
+    pub fn media_ids(&self) -> MediaIds {
+        let id = |key: &str| self.provider_ids.get(key).filter(|id| !id.is_empty());
+        MediaIds {
+            trakt: None,
+            imdb: id("Imdb").cloned(),
+            tmdb: id("Tmdb").and_then(|id| id.parse().ok()),
+            tvdb: id("Tvdb").and_then(|id| id.parse().ok()),
+        }
+    }
+}
+
+/// A video, audio or subtitle stream of an item.
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct MediaStream {
+    /// `Video`, `Audio`, `Subtitle` and so on.
+    pub r#type: String,
+    pub codec: Option<String>,
+    /// Codec profile, for audio such as `DTS-HD MA` or `Dolby TrueHD + Dolby Atmos`.
+    pub profile: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Dynamic range format such as `SDR`, `HDR10`, `HDR10Plus`, `HLG` or
+    /// `DOVI`.
+    pub video_range_type: Option<String>,
+    pub channels: Option<u32>,
+    /// Such as `stereo` or `5.1(side)`.
+    pub channel_layout: Option<String>,
+    #[serde(default)]
+    pub is_default: bool,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ItemsResult {
+    pub items: Vec<BaseItem>,
+    pub total_record_count: u64,
+    pub start_index: u64,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct SessionInfo {
+    pub id: String,
+    pub user_id: Option<String>,
+    pub user_name: Option<String>,
+    pub client: Option<String>,
+    pub device_name: Option<String>,
+    pub now_playing_item: Option<BaseItem>,
+    pub play_state: Option<PlayState>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PlayState {
+    /// Playback position in ticks of 100ns.
+    pub position_ticks: Option<u64>,
+    #[serde(default)]
+    pub is_paused: bool,
+}
@@ -1,4 +1,13 @@
 pub mod base;
+pub mod cache;
+mod error;
 pub mod jellyfin;
+pub mod media;
+pub mod pagination;
+pub mod ratelimit;
+pub mod realdebrid;
+pub mod retry;
 pub mod seerrs;
 pub mod trakt;
+
+pub use error::Error;
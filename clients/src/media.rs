@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MediaType {
+    Movie,
+    Show,
+}
+
+/// External ids of a title, as far as they are known.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MediaIds {
+    pub trakt: Option<u64>,
+    pub imdb: Option<String>,
+    pub tmdb: Option<u64>,
+    pub tvdb: Option<u64>,
+}
+
+impl MediaIds {
+    /// Whether both sets of ids refer to the same title. Only ids known on
+    /// both sides are compared, IMDb first and TMDB second.
+    pub fn matches(&self, other: &MediaIds) -> bool {
+        match (&self.imdb, &other.imdb) {
+            (Some(a), Some(b)) => a == b,
+            _ => matches!((self.tmdb, other.tmdb), (Some(a), Some(b)) if a == b),
+        }
+    }
+
+    /// Fills in the ids missing here from `other`.
+    pub fn merge(&mut self, other: &MediaIds) {
+        self.trakt = self.trakt.or(other.trakt);
+        self.imdb = self.imdb.take().or_else(|| other.imdb.clone());
+        self.tmdb = self.tmdb.or(other.tmdb);
+        self.tvdb = self.tvdb.or(other.tvdb);
+    }
+}
+
+/// Provider-independent identity of a movie or show, optionally narrowed down
+/// to a single season or episode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaIdentity {
+    pub media_type: MediaType,
+    pub title: Option<String>,
+    pub year: Option<u32>,
+    pub ids: MediaIds,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+}
+
+impl MediaIdentity {
+    pub fn new(media_type: MediaType, ids: MediaIds) -> Self {
+        Self {
+            media_type,
+            title: None,
+            year: None,
+            ids,
+            season: None,
+            episode: None,
+        }
+    }
+
+    /// Whether both identities point at the same media, regardless of which
+    /// provider they came from.
+    pub fn is_same(&self, other: &MediaIdentity) -> bool {
+        self.media_type == other.media_type
+            && self.season == other.season
+            && self.episode == other.episode
+            && self.ids.matches(&other.ids)
+    }
+}
@@ -0,0 +1,233 @@
+use std::future::Future;
+
+use futures::stream::{self, Stream, TryStreamExt};
+
+use crate::Error;
+
+/// One page of results as returned by a paginated endpoint.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Total number of items across all pages, when the service reports it.
+    pub total: Option<u64>,
+    /// Total number of pages, when the service reports it.
+    pub page_count: Option<u64>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            items,
+            total: None,
+            page_count: None,
+        }
+    }
+}
+
+/// Streams every item of an endpoint paginated by offset and page size, like
+/// Seerr's `skip`/`take` or Jellyfin's `StartIndex`/`Limit`.
+///
+/// `fetch` is called with the offset and page size of each page. Pages are
+/// requested until one comes back short or the reported total is reached.
+pub fn offset_paginated<'a, T, F, Fut>(
+    page_size: u64,
+    mut fetch: F,
+) -> impl Stream<Item = Result<T, Error>> + 'a
+where
+    T: 'a,
+    F: FnMut(u64, u64) -> Fut + 'a,
+    Fut: Future<Output = Result<Page<T>, Error>> + 'a,
+{
+    let page_size = page_size.max(1);
+    stream::try_unfold(Some(0), move |offset| {
+        let next = offset.map(|offset| fetch(offset, page_size));
+        async move {
+            let (Some(offset), Some(next)) = (offset, next) else {
+                return Ok(None);
+            };
+            let page = next.await?;
+            let fetched = offset + page.items.len() as u64;
+            let exhausted = (page.items.len() as u64) < page_size
+                || page.total.is_some_and(|total| fetched >= total);
+            let offset = (!exhausted).then_some(fetched);
+            Ok(Some((page.items, offset)))
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+/// Streams every item of an endpoint paginated by a 1-based page number and
+/// a limit, like Trakt's `page`/`limit`.
+///
+/// `fetch` is called with the page number and limit of each page. Pages are
+/// requested until one comes back short or the reported page count is reached.
+pub fn page_paginated<'a, T, F, Fut>(
+    limit: u64,
+    mut fetch: F,
+) -> impl Stream<Item = Result<T, Error>> + 'a
+where
+    T: 'a,
+    F: FnMut(u64, u64) -> Fut + 'a,
+    Fut: Future<Output = Result<Page<T>, Error>> + 'a,
+{
+    let limit = limit.max(1);
+    stream::try_unfold(Some(1), move |page_number| {
+        let next = page_number.map(|page_number| fetch(page_number, limit));
+        async move {
+            let (Some(page_number), Some(next)) = (page_number, next) else {
+                return Ok(None);
+            };
+            let page = next.await?;
+            let exhausted = match page.page_count {
+                Some(page_count) => page_number >= page_count,
+                None => (page.items.len() as u64) < limit,
+            };
+            let page_number = (!exhausted).then_some(page_number + 1);
+            Ok(Some((page.items, page_number)))
+        }
+    })
+    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+    .try_flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::future::{ready, Ready};
+
+    use futures::executor::block_on;
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    /// A fake offset-paginated endpoint over `0..len` that records every
+    /// `(offset, page_size)` it is called with.
+    fn offset_endpoint(
+        len: u64,
+        report_total: bool,
+        calls: &RefCell<Vec<(u64, u64)>>,
+    ) -> impl FnMut(u64, u64) -> Ready<Result<Page<u64>, Error>> + '_ {
+        move |offset, page_size| {
+            calls.borrow_mut().push((offset, page_size));
+            let mut page = Page::new((offset..len.min(offset + page_size)).collect());
+            if report_total {
+                page.total = Some(len);
+            }
+            ready(Ok(page))
+        }
+    }
+
+    /// A fake page-paginated endpoint over `0..len` that records every
+    /// `(page, limit)` it is called with.
+    fn page_endpoint(
+        len: u64,
+        page_count: Option<u64>,
+        calls: &RefCell<Vec<(u64, u64)>>,
+    ) -> impl FnMut(u64, u64) -> Ready<Result<Page<u64>, Error>> + '_ {
+        move |page_number, limit| {
+            calls.borrow_mut().push((page_number, limit));
+            let start = (page_number - 1) * limit;
+            let mut page = Page::new((start.min(len)..len.min(start + limit)).collect());
+            page.page_count = page_count;
+            ready(Ok(page))
+        }
+    }
+
+    #[test]
+    fn offset_stops_at_short_last_page() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(offset_paginated(10, offset_endpoint(25, false, &calls)).try_collect())
+                .unwrap();
+        assert_eq!(items, (0..25).collect::<Vec<_>>());
+        assert_eq!(*calls.borrow(), [(0, 10), (10, 10), (20, 10)]);
+    }
+
+    #[test]
+    fn offset_stops_at_total_on_exact_multiple() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(offset_paginated(10, offset_endpoint(20, true, &calls)).try_collect())
+                .unwrap();
+        assert_eq!(items.len(), 20);
+        assert_eq!(*calls.borrow(), [(0, 10), (10, 10)]);
+    }
+
+    #[test]
+    fn offset_without_total_fetches_empty_page_after_exact_multiple() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(offset_paginated(10, offset_endpoint(20, false, &calls)).try_collect())
+                .unwrap();
+        assert_eq!(items.len(), 20);
+        assert_eq!(*calls.borrow(), [(0, 10), (10, 10), (20, 10)]);
+    }
+
+    #[test]
+    fn offset_empty_first_page() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(offset_paginated(10, offset_endpoint(0, true, &calls)).try_collect()).unwrap();
+        assert!(items.is_empty());
+        assert_eq!(*calls.borrow(), [(0, 10)]);
+    }
+
+    #[test]
+    fn page_stops_at_short_last_page() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(page_paginated(10, page_endpoint(25, None, &calls)).try_collect()).unwrap();
+        assert_eq!(items, (0..25).collect::<Vec<_>>());
+        assert_eq!(*calls.borrow(), [(1, 10), (2, 10), (3, 10)]);
+    }
+
+    #[test]
+    fn page_stops_at_page_count_on_exact_multiple() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(page_paginated(10, page_endpoint(20, Some(2), &calls)).try_collect()).unwrap();
+        assert_eq!(items.len(), 20);
+        assert_eq!(*calls.borrow(), [(1, 10), (2, 10)]);
+    }
+
+    #[test]
+    fn page_count_of_zero_or_one_fetches_one_page() {
+        for (len, page_count) in [(0, 0), (5, 1), (10, 1)] {
+            let calls = RefCell::new(Vec::new());
+            let items: Vec<u64> = block_on(
+                page_paginated(10, page_endpoint(len, Some(page_count), &calls)).try_collect(),
+            )
+            .unwrap();
+            assert_eq!(items.len() as u64, len);
+            assert_eq!(*calls.borrow(), [(1, 10)]);
+        }
+    }
+
+    #[test]
+    fn page_empty_first_page() {
+        let calls = RefCell::new(Vec::new());
+        let items: Vec<u64> =
+            block_on(page_paginated(10, page_endpoint(0, None, &calls)).try_collect()).unwrap();
+        assert!(items.is_empty());
+        assert_eq!(*calls.borrow(), [(1, 10)]);
+    }
+
+    #[test]
+    fn errors_end_the_stream() {
+        let mut calls = 0;
+        let result: Result<Vec<u64>, Error> = block_on(
+            page_paginated(10, |_, _| {
+                calls += 1;
+                ready(Err::<Page<u64>, _>(Error::from_status(
+                    "http://example.test/items".to_string(),
+                    reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+                    String::new(),
+                )))
+            })
+            .try_collect(),
+        );
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
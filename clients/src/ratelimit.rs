@@ -0,0 +1,280 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::retry::retry_after;
+
+/// Token-bucket rate limiter. Clones share the same bucket, so one limiter
+/// can throttle every `HttpClient` talking to the same service.
+///
+/// The bucket also adapts to what the service reports: the `X-Ratelimit`
+/// header sent by Trakt, the common `X-RateLimit-Remaining` and
+/// `X-RateLimit-Reset` headers, and `Retry-After` on `429 Too Many Requests`.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+    paused_until: Option<Instant>,
+}
+
+/// Longest pause a service's rate limit headers can impose. Anything longer
+/// is more likely a misread header than a real limit.
+const MAX_PAUSE: Duration = Duration::from_secs(15 * 60);
+
+/// `X-RateLimit-Reset` values above this many seconds are taken as a Unix
+/// timestamp rather than a delay.
+const EPOCH_THRESHOLD: u64 = 365 * 24 * 60 * 60;
+
+/// Payload of the `X-Ratelimit` header Trakt sends with every response.
+#[derive(Debug, Deserialize)]
+struct TraktRateLimit {
+    period: u64,
+    remaining: u64,
+    /// When the current period ends.
+    until: Option<DateTime<Utc>>,
+}
+
+impl RateLimiter {
+    /// Allows `limit` requests per `period`, all of which may be sent in a
+    /// single burst.
+    pub fn new(limit: u32, period: Duration) -> Self {
+        let capacity = f64::from(limit.max(1));
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                capacity,
+                tokens: capacity,
+                refill_per_sec: capacity / period.as_secs_f64(),
+                last_refill: Instant::now(),
+                paused_until: None,
+            })),
+        }
+    }
+
+    /// Caps how many requests may be sent back to back before the limiter
+    /// starts spacing them out at the sustained rate.
+    pub fn with_burst(self, burst: u32) -> Self {
+        {
+            let mut bucket = self.bucket.lock().unwrap();
+            bucket.capacity = f64::from(burst.max(1));
+            bucket.tokens = bucket.tokens.min(bucket.capacity);
+        }
+        self
+    }
+
+    /// Trakt allows 1000 authenticated `GET` calls every five minutes.
+    pub fn trakt() -> Self {
+        Self::new(1000, Duration::from_secs(5 * 60)).with_burst(20)
+    }
+
+    /// Real-Debrid allows 250 requests per minute.
+    pub fn real_debrid() -> Self {
+        Self::new(250, Duration::from_secs(60)).with_burst(10)
+    }
+
+    /// Waits until a request may be sent and takes a token for it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = self.bucket.lock().unwrap().try_take(Instant::now());
+            match wait {
+                None => return,
+                Some(wait) => {
+                    log::debug!("Rate limit reached, waiting {wait:?}");
+                    tokio::time::sleep(wait).await;
+                }
+            }
+        }
+    }
+
+    /// Adjusts the bucket to the limits reported in a response.
+    pub(crate) fn observe(
+        &self,
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+    ) {
+        let now = Instant::now();
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(now);
+
+        if let Some(limit) = header_str(headers, "x-ratelimit")
+            .and_then(|value| serde_json::from_str::<TraktRateLimit>(value).ok())
+        {
+            bucket.tokens = bucket.tokens.min(limit.remaining as f64);
+            if limit.remaining == 0 {
+                let wait = match limit.until {
+                    Some(until) => (until - Utc::now()).to_std().unwrap_or(Duration::ZERO),
+                    None => Duration::from_secs(limit.period),
+                };
+                bucket.pause(now + wait.min(MAX_PAUSE));
+            }
+        } else if let Some(remaining) =
+            header_str(headers, "x-ratelimit-remaining").and_then(|value| value.parse::<f64>().ok())
+        {
+            bucket.tokens = bucket.tokens.min(remaining);
+            let reset = header_str(headers, "x-ratelimit-reset")
+                .and_then(|value| value.parse::<u64>().ok());
+            if let (0.0, Some(reset)) = (remaining, reset) {
+                bucket.pause(now + reset_delay(reset, SystemTime::now()));
+            }
+        }
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            bucket.tokens = 0.0;
+            let wait = retry_after(headers).unwrap_or(Duration::from_secs(1));
+            bucket.pause(now + wait.min(MAX_PAUSE));
+        }
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// Takes a token, or returns how long to wait before trying again.
+    fn try_take(&mut self, now: Instant) -> Option<Duration> {
+        if let Some(until) = self.paused_until {
+            if until > now {
+                return Some(until - now);
+            }
+            self.paused_until = None;
+        }
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    fn pause(&mut self, until: Instant) {
+        self.paused_until = Some(
+            self.paused_until
+                .map_or(until, |current| current.max(until)),
+        );
+    }
+}
+
+/// How long to wait for an `X-RateLimit-Reset` value, which services send
+/// either as seconds from now or as the Unix timestamp of the reset.
+fn reset_delay(reset: u64, now: SystemTime) -> Duration {
+    let delay = if reset > EPOCH_THRESHOLD {
+        let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        Duration::from_secs(reset.saturating_sub(now))
+    } else {
+        Duration::from_secs(reset)
+    };
+    delay.min(MAX_PAUSE)
+}
+
+fn header_str<'a>(headers: &'a reqwest::header::HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    const NOW: u64 = 1_700_000_000;
+
+    #[test]
+    fn small_resets_are_delays() {
+        assert_eq!(reset_delay(30, at(NOW)), Duration::from_secs(30));
+        assert_eq!(reset_delay(0, at(NOW)), Duration::ZERO);
+        assert_eq!(reset_delay(EPOCH_THRESHOLD, at(NOW)), MAX_PAUSE);
+    }
+
+    #[test]
+    fn large_resets_are_timestamps() {
+        assert_eq!(reset_delay(NOW + 42, at(NOW)), Duration::from_secs(42));
+        assert_eq!(reset_delay(EPOCH_THRESHOLD + 1, at(NOW)), Duration::ZERO);
+        assert_eq!(reset_delay(NOW - 5, at(NOW)), Duration::ZERO);
+    }
+
+    #[test]
+    fn resets_are_capped() {
+        assert_eq!(reset_delay(24 * 60 * 60, at(NOW)), MAX_PAUSE);
+        assert_eq!(reset_delay(NOW + 24 * 60 * 60, at(NOW)), MAX_PAUSE);
+    }
+
+    fn bucket(capacity: f64, refill_per_sec: f64, now: Instant) -> Bucket {
+        Bucket {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: now,
+            paused_until: None,
+        }
+    }
+
+    #[test]
+    fn try_take_spends_the_burst_then_waits_for_refill() {
+        let now = Instant::now();
+        let mut bucket = bucket(2.0, 1.0, now);
+        assert_eq!(bucket.try_take(now), None);
+        assert_eq!(bucket.try_take(now), None);
+        assert_eq!(bucket.try_take(now), Some(Duration::from_secs(1)));
+
+        let later = now + Duration::from_millis(1500);
+        assert_eq!(bucket.try_take(later), None);
+        assert_eq!(bucket.try_take(later), Some(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn try_take_waits_out_pauses() {
+        let now = Instant::now();
+        let mut bucket = bucket(10.0, 1.0, now);
+        bucket.pause(now + Duration::from_secs(5));
+        bucket.pause(now + Duration::from_secs(2));
+        assert_eq!(bucket.try_take(now), Some(Duration::from_secs(5)));
+        assert_eq!(bucket.try_take(now + Duration::from_secs(5)), None);
+        assert_eq!(bucket.paused_until, None);
+    }
+
+    #[test]
+    fn long_retry_after_is_capped() {
+        let limiter = RateLimiter::new(10, Duration::from_secs(1));
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(reqwest::header::RETRY_AFTER, "86400".parse().unwrap());
+        let before = Instant::now();
+        limiter.observe(reqwest::StatusCode::TOO_MANY_REQUESTS, &headers);
+        let paused_until = limiter.bucket.lock().unwrap().paused_until.unwrap();
+        assert!(paused_until <= before + MAX_PAUSE + Duration::from_secs(1));
+        assert!(paused_until >= before + MAX_PAUSE);
+    }
+
+    #[test]
+    fn trakt_header_pauses_until_the_period_ends() {
+        let limiter = RateLimiter::trakt();
+        let until = (Utc::now() + chrono::Duration::seconds(30)).to_rfc3339();
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            "x-ratelimit",
+            format!(r#"{{"period": 300, "remaining": 0, "until": "{until}"}}"#)
+                .parse()
+                .unwrap(),
+        );
+        let before = Instant::now();
+        limiter.observe(reqwest::StatusCode::OK, &headers);
+        let paused_until = limiter.bucket.lock().unwrap().paused_until.unwrap();
+        assert!(paused_until <= before + Duration::from_secs(31));
+        assert!(paused_until >= before + Duration::from_secs(28));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::base::{header_value, HttpClient, NoContent};
+use crate::ratelimit::RateLimiter;
+use crate::realdebrid::structs::{
+    AddedTorrent, Download, Torrent, TorrentInfo, Traffic, UnrestrictedLink, User,
+};
+use crate::retry::RetryPolicy;
+use crate::Error;
+
+#[derive(Clone)]
+pub struct RealDebridClient {
+    client: HttpClient,
+}
+
+impl RealDebridClient {
+    pub fn new(token: &str) -> Result<Self, Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            reqwest::header::AUTHORIZATION,
+            header_value(&format!("Bearer {token}"))?,
+        );
+        Ok(Self {
+            client: HttpClient::new("https://api.real-debrid.com/rest/1.0", Some(headers))?
+                .with_rate_limiter(RateLimiter::real_debrid()),
+        })
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.with_retry_policy(policy);
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.client = self.client.with_rate_limiter(limiter);
+        self
+    }
+
+    pub async fn get_user(&self) -> Result<User, Error> {
+        self.client.get("/user").send::<User>().await
+    }
+
+    pub async fn get_torrents(&self, page: u64, limit: u64) -> Result<Vec<Torrent>, Error> {
+        self.client
+            .get("/torrents")
+            .query(&[("page", page), ("limit", limit)])
+            .send::<Vec<Torrent>>()
+            .await
+    }
+
+    pub async fn get_torrent_info(&self, id: &str) -> Result<TorrentInfo, Error> {
+        self.client
+            .get(format!("/torrents/info/{id}"))
+            .send::<TorrentInfo>()
+            .await
+    }
+
+    pub async fn add_magnet(&self, magnet: &str) -> Result<AddedTorrent, Error> {
+        self.client
+            .post("/torrents/addMagnet")
+            .form(&[("magnet", magnet)])
+            .send::<AddedTorrent>()
+            .await
+    }
+
+    /// Selects which files of a torrent to download. `None` selects every file.
+    pub async fn select_files(
+        &self,
+        id: &str,
+        file_ids: Option<&[u64]>,
+    ) -> Result<NoContent, Error> {
+        let files = match file_ids {
+            Some(ids) => ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            None => "all".to_string(),
+        };
+        self.client
+            .post(format!("/torrents/selectFiles/{id}"))
+            .form(&[("files", files)])
+            .send::<NoContent>()
+            .await
+    }
+
+    pub async fn delete_torrent(&self, id: &str) -> Result<NoContent, Error> {
+        self.client
+            .delete(format!("/torrents/delete/{id}"))
+            .send::<NoContent>()
+            .await
+    }
+
+    pub async fn unrestrict_link(&self, link: &str) -> Result<UnrestrictedLink, Error> {
+        self.client
+            .post("/unrestrict/link")
+            .form(&[("link", link)])
+            .send::<UnrestrictedLink>()
+            .await
+    }
+
+    pub async fn get_downloads(&self, page: u64, limit: u64) -> Result<Vec<Download>, Error> {
+        self.client
+            .get("/downloads")
+            .query(&[("page", page), ("limit", limit)])
+            .send::<Vec<Download>>()
+            .await
+    }
+
+    pub async fn get_traffic(&self) -> Result<Traffic, Error> {
+        self.client.get("/traffic").send::<Traffic>().await
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+    pub email: String,
+    pub points: u64,
+    pub locale: String,
+    pub avatar: String,
+    pub r#type: String,
+    pub premium: u64,
+    pub expiration: String,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct Torrent {
+    pub id: String,
+    pub filename: String,
+    pub hash: String,
+    pub bytes: u64,
+    pub host: String,
+    pub split: u64,
+    pub progress: f64,
+    pub status: String,
+    pub added: String,
+    pub links: Vec<String>,
+    pub ended: Option<String>,
+    pub speed: Option<u64>,
+    pub seeders: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct TorrentFile {
+    pub id: u64,
+    pub path: String,
+    pub bytes: u64,
+    pub selected: u8,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct TorrentInfo {
+    pub id: String,
+    pub filename: String,
+    pub original_filename: String,
+    pub hash: String,
+    pub bytes: u64,
+    pub original_bytes: u64,
+    pub host: String,
+    pub split: u64,
+    pub progress: f64,
+    pub status: String,
+    pub added: String,
+    pub files: Vec<TorrentFile>,
+    pub links: Vec<String>,
+    pub ended: Option<String>,
+    pub speed: Option<u64>,
+    pub seeders: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct AddedTorrent {
+    pub id: String,
+    pub uri: String,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnrestrictedLink {
+    pub id: String,
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub filesize: u64,
+    pub link: String,
+    pub host: String,
+    pub chunks: u64,
+    pub crc: Option<u64>,
+    pub download: String,
+    pub streamable: Option<u8>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Download {
+    pub id: String,
+    pub filename: String,
+    pub mime_type: Option<String>,
+    pub filesize: u64,
+    pub link: String,
+    pub host: String,
+    pub chunks: u64,
+    pub download: String,
+    pub generated: String,
+    pub streamable: Option<u8>,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct HostTraffic {
+    pub left: Option<u64>,
+    pub bytes: Option<u64>,
+    pub links: Option<u64>,
+    pub limit: Option<u64>,
+    pub r#type: String,
+    pub extra: Option<u64>,
+    pub reset: String,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(transparent)]
+pub struct Traffic {
+    pub hosts: HashMap<String, HostTraffic>,
+}
@@ -0,0 +1,213 @@
+use std::time::{Duration, SystemTime};
+
+use rand::Rng;
+use reqwest::{Method, StatusCode};
+
+/// Controls how `HttpClient` retries failed requests.
+///
+/// Only requests whose method is listed in `retryable_methods` are retried,
+/// so non-idempotent calls such as `POST` are sent exactly once unless a
+/// client opts in.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the computed backoff.
+    pub max_backoff: Duration,
+    /// Factor the backoff grows by after every attempt.
+    pub multiplier: f64,
+    /// Fraction of each delay, between 0 and 1, that is randomized.
+    pub jitter: f64,
+    pub retryable_methods: Vec<Method>,
+    pub retryable_statuses: Vec<StatusCode>,
+    /// Wait for the duration given by a `Retry-After` header instead of the
+    /// computed backoff when the service sends one. A request asked to wait
+    /// longer than `max_backoff` is not retried.
+    pub respect_retry_after: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: 0.5,
+            retryable_methods: vec![
+                Method::GET,
+                Method::HEAD,
+                Method::OPTIONS,
+                Method::PUT,
+                Method::DELETE,
+            ],
+            retryable_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            respect_retry_after: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that sends every request exactly once.
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn retries_method(&self, method: &Method) -> bool {
+        self.retryable_methods.contains(method)
+    }
+
+    pub(crate) fn retries_status(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status)
+    }
+
+    /// Whether a transport error is worth another attempt. Errors raised
+    /// while building the request or following redirects are not.
+    pub(crate) fn retries_error(error: &reqwest::Error) -> bool {
+        error.is_connect() || error.is_timeout() || error.is_request()
+    }
+
+    /// The delay before retrying after the given (1-based) attempt got a
+    /// retryable response, or `None` if its `Retry-After` asks for longer
+    /// than `max_backoff`.
+    pub(crate) fn delay(
+        &self,
+        attempt: u32,
+        headers: &reqwest::header::HeaderMap,
+    ) -> Option<Duration> {
+        if self.respect_retry_after {
+            if let Some(delay) = retry_after(headers) {
+                return (delay <= self.max_backoff).then_some(delay);
+            }
+        }
+        Some(self.backoff(attempt))
+    }
+
+    /// The computed delay before retrying after the given (1-based) attempt
+    /// failed.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        // Clamped as a float, since the unclamped delay can overflow a
+        // `Duration` after enough attempts.
+        let secs = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = Duration::try_from_secs_f64(secs.min(self.max_backoff.as_secs_f64()))
+            .unwrap_or(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        backoff.mul_f64(1.0 - jitter * rand::thread_rng().gen::<f64>())
+    }
+}
+
+/// Parses a `Retry-After` header given either in seconds or as an HTTP date.
+pub(crate) fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers.get(reqwest::header::RETRY_AFTER)?.to_str().ok()?;
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            jitter: 0.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    fn retry_after_header(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn backoff_grows_up_to_max() {
+        let policy = policy();
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(3), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10), policy.max_backoff);
+    }
+
+    #[test]
+    fn backoff_survives_huge_attempts_and_multipliers() {
+        let policy = policy();
+        assert_eq!(policy.backoff(100), policy.max_backoff);
+        assert_eq!(policy.backoff(u32::MAX), policy.max_backoff);
+
+        let policy = RetryPolicy {
+            multiplier: 1e300,
+            ..policy
+        };
+        assert_eq!(policy.backoff(3), policy.max_backoff);
+    }
+
+    #[test]
+    fn jitter_only_shortens_the_backoff() {
+        let policy = RetryPolicy {
+            jitter: 1.0,
+            ..RetryPolicy::default()
+        };
+        for _ in 0..100 {
+            assert!(policy.backoff(2) <= Duration::from_secs(1));
+        }
+    }
+
+    #[test]
+    fn delay_honors_retry_after_up_to_max_backoff() {
+        let policy = policy();
+        assert_eq!(
+            policy.delay(1, &retry_after_header("7")),
+            Some(Duration::from_secs(7))
+        );
+        assert_eq!(policy.delay(1, &retry_after_header("86400")), None);
+        assert_eq!(
+            policy.delay(2, &HeaderMap::new()),
+            Some(Duration::from_secs(1))
+        );
+
+        let ignoring = RetryPolicy {
+            respect_retry_after: false,
+            ..policy
+        };
+        assert_eq!(
+            ignoring.delay(1, &retry_after_header("86400")),
+            Some(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn retry_after_parses_seconds_and_dates() {
+        assert_eq!(
+            retry_after(&retry_after_header(" 12 ")),
+            Some(Duration::from_secs(12))
+        );
+        assert_eq!(
+            retry_after(&retry_after_header("Wed, 21 Oct 2015 07:28:00 GMT")),
+            Some(Duration::ZERO)
+        );
+        let soon = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let delay = retry_after(&retry_after_header(&soon)).unwrap();
+        assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+        assert_eq!(retry_after(&retry_after_header("soon")), None);
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+}
@@ -1,35 +1,138 @@
-use std::collections::HashMap;
+use futures::{Stream, TryStreamExt};
+use serde::Serialize;
 
-use crate::base::HttpClient;
-use crate::seerrs::structs::Requests;
+use crate::base::{header_value, HttpClient, NoContent};
+use crate::pagination::{offset_paginated, Page};
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::seerrs::structs::{MediaInfo, MediaRequest, MediaStatusUpdate, RequestUpdate, Requests};
+use crate::Error;
 
+const PAGE_SIZE: u64 = 100;
+
+#[derive(Debug, Serialize)]
+struct MediaStatusRequest {
+    is4k: bool,
+}
+
+#[derive(Clone)]
 pub struct SeerrClient {
     client: HttpClient,
 }
 
 impl SeerrClient {
-    pub fn new(base_url: &str, token: &str) -> Self {
+    pub fn new(base_url: &str, token: &str) -> Result<Self, Error> {
         let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("X-Api-Key", token.parse().unwrap());
-        Self {
-            client: HttpClient::new(base_url, Some(headers)).unwrap(),
-        }
-    }
-
-    pub async fn get_unfulfilled_requests(&self) -> Result<Requests, reqwest::Error> {
-        let params = HashMap::from([
-            ("take".to_string(), "1000".to_string()),
-            ("skip".to_string(), "0".to_string()),
-            ("filter".to_string(), "processing".to_string()),
-        ]);
+        headers.insert("X-Api-Key", header_value(token)?);
+        Ok(Self {
+            client: HttpClient::new(base_url, Some(headers))?,
+        })
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.with_retry_policy(policy);
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.client = self.client.with_rate_limiter(limiter);
+        self
+    }
+
+    /// Streams every request matching `filter` (e.g. `processing`,
+    /// `pending` or `all`), fetching further pages as the stream is consumed.
+    pub fn requests<'a>(
+        &'a self,
+        filter: &'a str,
+    ) -> impl Stream<Item = Result<MediaRequest, Error>> + 'a {
+        offset_paginated(PAGE_SIZE, move |skip, take| async move {
+            let requests = self
+                .client
+                .get("/api/v1/request")
+                .query(&[("take", take), ("skip", skip)])
+                .query(&[("filter", filter)])
+                .send::<Requests>()
+                .await?;
+            Ok(Page {
+                items: requests.results,
+                total: Some(requests.page_info.results),
+                page_count: Some(requests.page_info.pages),
+            })
+        })
+    }
+
+    pub async fn get_unfulfilled_requests(&self) -> Result<Vec<MediaRequest>, Error> {
+        self.requests("processing").try_collect().await
+    }
+
+    /// Requests waiting for an admin's approval.
+    pub async fn get_pending_requests(&self) -> Result<Vec<MediaRequest>, Error> {
+        self.requests("pending").try_collect().await
+    }
+
+    pub async fn get_request(&self, id: u64) -> Result<MediaRequest, Error> {
+        self.client
+            .get(format!("/api/v1/request/{id}"))
+            .send::<MediaRequest>()
+            .await
+    }
+
+    pub async fn approve_request(&self, id: u64) -> Result<MediaRequest, Error> {
+        self.set_request_status(id, "approve").await
+    }
+
+    pub async fn decline_request(&self, id: u64) -> Result<MediaRequest, Error> {
+        self.set_request_status(id, "decline").await
+    }
+
+    /// Sends a failed request to Sonarr/Radarr again.
+    pub async fn retry_request(&self, id: u64) -> Result<MediaRequest, Error> {
+        self.client
+            .post(format!("/api/v1/request/{id}/retry"))
+            .send::<MediaRequest>()
+            .await
+    }
+
+    pub async fn update_request(
+        &self,
+        id: u64,
+        update: &RequestUpdate,
+    ) -> Result<MediaRequest, Error> {
+        self.client
+            .put(format!("/api/v1/request/{id}"))
+            .json(update)
+            .send::<MediaRequest>()
+            .await
+    }
+
+    pub async fn delete_request(&self, id: u64) -> Result<NoContent, Error> {
+        self.client
+            .delete(format!("/api/v1/request/{id}"))
+            .send::<NoContent>()
+            .await
+    }
+
+    /// Sets the status of a media item, or of its 4K version if `is4k`.
+    /// `media_id` is Seerr's own id, `MediaInfo::id`. Marking a show
+    /// available marks all of its seasons available; Seerr has no way to set
+    /// a single season.
+    pub async fn set_media_status(
+        &self,
+        media_id: u64,
+        status: MediaStatusUpdate,
+        is4k: bool,
+    ) -> Result<MediaInfo, Error> {
+        self.client
+            .post(format!("/api/v1/media/{media_id}/{}", status.as_str()))
+            .json(&MediaStatusRequest { is4k })
+            .send::<MediaInfo>()
+            .await
+    }
+
+    async fn set_request_status(&self, id: u64, status: &str) -> Result<MediaRequest, Error> {
         self.client
-            .request::<Requests>(
-                reqwest::Method::GET,
-                "/api/v1/request",
-                Some(params),
-                None,
-                None,
-            )
+            .post(format!("/api/v1/request/{id}/{status}"))
+            .send::<MediaRequest>()
             .await
     }
 }
@@ -1,7 +1,10 @@
+use std::collections::HashMap;
+use std::ops::BitOr;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Deserialize, Debug, Serialize)]
-pub struct NoContent {}
+use crate::media;
 
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -10,72 +13,176 @@ pub struct PageInfo {
     pub pages: u64,
     pub results: u64,
     pub page_size: u64,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MediaInfo {
     pub id: u64,
+    pub media_type: Option<MediaType>,
     pub tmdb_id: u64,
     pub tvdb_id: Option<u64>,
-    pub status: u8,
-    //requests: Vec<HashMap<K, V>>,
+    pub imdb_id: Option<String>,
+    pub status: MediaStatus,
+    pub status4k: Option<MediaStatus>,
+    #[serde(default)]
+    pub seasons: Vec<Season>,
+    pub jellyfin_media_id: Option<String>,
+    pub jellyfin_media_id4k: Option<String>,
+    pub media_added_at: Option<String>,
     pub created_at: String,
     pub updated_at: String,
+    /// Fields not modelled here, kept so nothing is lost on a round trip.
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
+/// A Seerr account. Which of the `plex_*` and `jellyfin_*` fields are set
+/// depends on `user_type`.
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
-    avatar: String,
-    created_at: String,
-    display_name: String,
-    email: String,
-    id: u8,
-    jellyfin_auth_token: String,
-    jellyfin_device_id: String,
-    jellyfin_user_id: String,
-    jellyfin_username: String,
-    movie_quota_days: Option<u8>,
-    movie_quota_limit: Option<u8>,
-    permissions: u8,
-    plex_id: Option<u16>,
-    plex_token: Option<String>,
-    plex_username: Option<String>,
-    recovery_link_expiration_date: Option<String>,
-    request_count: u8,
-    tv_quota_days: Option<u16>,
-    tv_quota_limit: Option<u16>,
-    updated_at: String,
-    user_type: u8,
-    username: Option<String>,
-    warnings: Vec<String>,
+    pub id: u64,
+    pub avatar: Option<String>,
+    pub created_at: String,
+    pub display_name: String,
+    pub email: Option<String>,
+    pub jellyfin_auth_token: Option<String>,
+    pub jellyfin_device_id: Option<String>,
+    pub jellyfin_user_id: Option<String>,
+    pub jellyfin_username: Option<String>,
+    pub movie_quota_days: Option<u32>,
+    pub movie_quota_limit: Option<u32>,
+    pub permissions: Permissions,
+    pub plex_id: Option<u64>,
+    pub plex_token: Option<String>,
+    pub plex_username: Option<String>,
+    pub recovery_link_expiration_date: Option<String>,
+    #[serde(default)]
+    pub request_count: u64,
+    pub tv_quota_days: Option<u32>,
+    pub tv_quota_limit: Option<u32>,
+    pub updated_at: String,
+    pub user_type: UserType,
+    pub username: Option<String>,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 #[serde(rename_all = "camelCase")]
 pub enum UserOrString {
-    User(User),
+    User(Box<User>),
     String(String),
 }
 
+/// Seerr permission bits, as stored in `User::permissions`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Permissions(u64);
+
+impl Permissions {
+    pub const NONE: Permissions = Permissions(0);
+    pub const ADMIN: Permissions = Permissions(1 << 1);
+    pub const MANAGE_SETTINGS: Permissions = Permissions(1 << 2);
+    pub const MANAGE_USERS: Permissions = Permissions(1 << 3);
+    pub const MANAGE_REQUESTS: Permissions = Permissions(1 << 4);
+    pub const REQUEST: Permissions = Permissions(1 << 5);
+    pub const VOTE: Permissions = Permissions(1 << 6);
+    pub const AUTO_APPROVE: Permissions = Permissions(1 << 7);
+    pub const AUTO_APPROVE_MOVIE: Permissions = Permissions(1 << 8);
+    pub const AUTO_APPROVE_TV: Permissions = Permissions(1 << 9);
+    pub const REQUEST_4K: Permissions = Permissions(1 << 10);
+    pub const REQUEST_4K_MOVIE: Permissions = Permissions(1 << 11);
+    pub const REQUEST_4K_TV: Permissions = Permissions(1 << 12);
+    pub const REQUEST_ADVANCED: Permissions = Permissions(1 << 13);
+    pub const REQUEST_VIEW: Permissions = Permissions(1 << 14);
+    pub const AUTO_APPROVE_4K: Permissions = Permissions(1 << 15);
+    pub const AUTO_APPROVE_4K_MOVIE: Permissions = Permissions(1 << 16);
+    pub const AUTO_APPROVE_4K_TV: Permissions = Permissions(1 << 17);
+    pub const REQUEST_MOVIE: Permissions = Permissions(1 << 18);
+    pub const REQUEST_TV: Permissions = Permissions(1 << 19);
+    pub const MANAGE_ISSUES: Permissions = Permissions(1 << 20);
+    pub const VIEW_ISSUES: Permissions = Permissions(1 << 21);
+    pub const CREATE_ISSUES: Permissions = Permissions(1 << 22);
+    pub const AUTO_REQUEST: Permissions = Permissions(1 << 23);
+    pub const AUTO_REQUEST_MOVIE: Permissions = Permissions(1 << 24);
+    pub const AUTO_REQUEST_TV: Permissions = Permissions(1 << 25);
+    pub const RECENT_VIEW: Permissions = Permissions(1 << 26);
+    pub const WATCHLIST_VIEW: Permissions = Permissions(1 << 27);
+    pub const MANAGE_BLACKLIST: Permissions = Permissions(1 << 28);
+    pub const VIEW_BLACKLIST: Permissions = Permissions(1 << 30);
+
+    /// Keeps unknown bits, so newer permissions survive a round trip.
+    pub const fn from_bits(bits: u64) -> Self {
+        Permissions(bits)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    /// Whether every bit of `other` is set.
+    pub const fn contains(&self, other: Permissions) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Whether the user may do what `other` guards. Admins may do anything,
+    /// as in Seerr itself.
+    pub const fn allows(&self, other: Permissions) -> bool {
+        self.contains(Permissions::ADMIN) || self.contains(other)
+    }
+}
+
+impl BitOr for Permissions {
+    type Output = Permissions;
+
+    fn bitor(self, other: Permissions) -> Permissions {
+        Permissions(self.0 | other.0)
+    }
+}
+
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Episode {
     pub id: u64,
-    pub season_number: u8,
-    pub episode_number: u8,
+    pub season_number: u32,
+    pub episode_number: u32,
     pub name: String,
 }
 
+/// A season of a `MediaInfo`.
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Season {
     pub id: u64,
-    pub season_number: u8,
-    pub status: u8,
+    pub season_number: u32,
+    pub status: MediaStatus,
+    pub status4k: Option<MediaStatus>,
     pub episodes: Option<Vec<Episode>>,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+/// A season asked for by a `MediaRequest`. Its status follows the request,
+/// not the media.
+#[derive(Deserialize, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeasonRequest {
+    pub id: u64,
+    pub season_number: u32,
+    pub status: RequestStatus,
+    pub created_at: Option<String>,
+    pub updated_at: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -87,13 +194,17 @@ pub struct MediaRequest {
     pub media: MediaInfo,
     pub modified_by: Option<UserOrString>,
     pub profile_id: Option<u64>,
+    pub language_profile_id: Option<u64>,
     pub requested_by: User,
     pub root_folder: Option<String>,
     pub server_id: Option<u64>,
-    pub status: u8,
-    pub seasons: Vec<Season>,
-    pub r#type: String,
+    pub status: RequestStatus,
+    #[serde(default)]
+    pub seasons: Vec<SeasonRequest>,
+    pub r#type: MediaType,
     pub updated_at: String,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Deserialize, Debug, Serialize)]
@@ -102,3 +213,218 @@ pub struct Requests {
     pub page_info: PageInfo,
     pub results: Vec<MediaRequest>,
 }
+
+/// Changes to an existing request. Unset fields are left as they are.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RequestUpdate {
+    pub media_type: MediaType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seasons: Option<Vec<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is4k: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_folder: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language_profile_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<u64>,
+}
+
+impl RequestUpdate {
+    pub fn new(media_type: MediaType) -> Self {
+        Self {
+            media_type,
+            seasons: None,
+            is4k: None,
+            server_id: None,
+            profile_id: None,
+            root_folder: None,
+            language_profile_id: None,
+            user_id: None,
+        }
+    }
+}
+
+/// States `SeerrClient::set_media_status` can put a media item in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaStatusUpdate {
+    Available,
+    PartiallyAvailable,
+    Processing,
+    Pending,
+    Unknown,
+}
+
+impl MediaStatusUpdate {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MediaStatusUpdate::Available => "available",
+            MediaStatusUpdate::PartiallyAvailable => "partial",
+            MediaStatusUpdate::Processing => "processing",
+            MediaStatusUpdate::Pending => "pending",
+            MediaStatusUpdate::Unknown => "unknown",
+        }
+    }
+}
+
+/// Where a request is in Seerr's approval flow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum RequestStatus {
+    PendingApproval,
+    Approved,
+    Declined,
+    Failed,
+    Completed,
+    Unknown(u8),
+}
+
+impl From<u8> for RequestStatus {
+    fn from(code: u8) -> Self {
+        match code {
+            1 => RequestStatus::PendingApproval,
+            2 => RequestStatus::Approved,
+            3 => RequestStatus::Declined,
+            4 => RequestStatus::Failed,
+            5 => RequestStatus::Completed,
+            other => RequestStatus::Unknown(other),
+        }
+    }
+}
+
+impl From<RequestStatus> for u8 {
+    fn from(status: RequestStatus) -> Self {
+        match status {
+            RequestStatus::PendingApproval => 1,
+            RequestStatus::Approved => 2,
+            RequestStatus::Declined => 3,
+            RequestStatus::Failed => 4,
+            RequestStatus::Completed => 5,
+            RequestStatus::Unknown(code) => code,
+        }
+    }
+}
+
+/// How much of a movie, show or season is available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum MediaStatus {
+    Unknown,
+    Pending,
+    Processing,
+    PartiallyAvailable,
+    Available,
+    Blacklisted,
+    Deleted,
+    /// A code this client does not know yet.
+    Other(u8),
+}
+
+impl From<u8> for MediaStatus {
+    fn from(code: u8) -> Self {
+        match code {
+            1 => MediaStatus::Unknown,
+            2 => MediaStatus::Pending,
+            3 => MediaStatus::Processing,
+            4 => MediaStatus::PartiallyAvailable,
+            5 => MediaStatus::Available,
+            6 => MediaStatus::Blacklisted,
+            7 => MediaStatus::Deleted,
+            other => MediaStatus::Other(other),
+        }
+    }
+}
+
+impl From<MediaStatus> for u8 {
+    fn from(status: MediaStatus) -> Self {
+        match status {
+            MediaStatus::Unknown => 1,
+            MediaStatus::Pending => 2,
+            MediaStatus::Processing => 3,
+            MediaStatus::PartiallyAvailable => 4,
+            MediaStatus::Available => 5,
+            MediaStatus::Blacklisted => 6,
+            MediaStatus::Deleted => 7,
+            MediaStatus::Other(code) => code,
+        }
+    }
+}
+
+/// The media server an account comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum UserType {
+    Plex,
+    Local,
+    Jellyfin,
+    Emby,
+    Unknown(u8),
+}
+
+impl From<u8> for UserType {
+    fn from(code: u8) -> Self {
+        match code {
+            1 => UserType::Plex,
+            2 => UserType::Local,
+            3 => UserType::Jellyfin,
+            4 => UserType::Emby,
+            other => UserType::Unknown(other),
+        }
+    }
+}
+
+impl From<UserType> for u8 {
+    fn from(user_type: UserType) -> Self {
+        match user_type {
+            UserType::Plex => 1,
+            UserType::Local => 2,
+            UserType::Jellyfin => 3,
+            UserType::Emby => 4,
+            UserType::Unknown(code) => code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum MediaType {
+    Movie,
+    Tv,
+    Unknown(String),
+}
+
+impl MediaType {
+    /// The provider-independent media type, if this is one.
+    pub fn as_media_type(&self) -> Option<media::MediaType> {
+        match self {
+            MediaType::Movie => Some(media::MediaType::Movie),
+            MediaType::Tv => Some(media::MediaType::Show),
+            MediaType::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<String> for MediaType {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "movie" => MediaType::Movie,
+            "tv" => MediaType::Tv,
+            _ => MediaType::Unknown(name),
+        }
+    }
+}
+
+impl From<MediaType> for String {
+    fn from(media_type: MediaType) -> Self {
+        match media_type {
+            MediaType::Movie => "movie".to_string(),
+            MediaType::Tv => "tv".to_string(),
+            MediaType::Unknown(name) => name,
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::base::HttpClient;
+use crate::retry::RetryPolicy;
+use crate::trakt::client::TraktClient;
+use crate::Error;
+
+const TRAKT_URL: &str = "https://api.trakt.tv";
+/// Extra delay Trakt asks for on every `slow_down` answer.
+const SLOW_DOWN_STEP: Duration = Duration::from_secs(5);
+
+/// An OAuth token as issued by Trakt's token endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraktOAuthToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Lifetime of the access token in seconds.
+    pub expires_in: u64,
+    /// Unix timestamp, in seconds, at which the token was issued.
+    pub created_at: u64,
+}
+
+impl TraktOAuthToken {
+    pub fn expires_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.created_at.saturating_add(self.expires_in))
+    }
+
+    /// Whether the access token expires within `margin` from now.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        SystemTime::now() + margin >= self.expires_at()
+    }
+}
+
+/// A pending device authorization. Show `user_code` and `verification_url`
+/// to the user, then wait for approval with `TraktClient::poll_device_token`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_url: String,
+    /// Seconds until the codes expire.
+    pub expires_in: u64,
+    /// Minimum number of seconds between two polls.
+    pub interval: u64,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceAuthError {
+    #[error("the device code expired before it was approved")]
+    Expired,
+    #[error("the user denied access")]
+    Denied,
+    #[error("the device code is invalid")]
+    InvalidCode,
+    #[error("the device code was already used")]
+    AlreadyUsed,
+    #[error("device authorization was cancelled")]
+    Cancelled,
+    #[error(transparent)]
+    Http(#[from] Error),
+}
+
+/// What a failed poll of the device token endpoint means for the flow.
+enum PollError {
+    Pending,
+    SlowDown,
+    Failed(DeviceAuthError),
+}
+
+impl From<Error> for PollError {
+    /// Trakt reports the state of a device code through status codes, while
+    /// RFC 8628 uses an `error` field; both are understood.
+    fn from(error: Error) -> Self {
+        let code = match &error {
+            Error::Status { payload, .. } | Error::Auth { payload, .. } => payload
+                .as_ref()
+                .and_then(|payload| payload.get("error")?.as_str()),
+            _ => None,
+        };
+        let status = error.status().map(|status| status.as_u16());
+        match (code, status) {
+            (Some("authorization_pending"), _) | (None, Some(400)) => PollError::Pending,
+            (Some("slow_down"), _) | (None, Some(429)) => PollError::SlowDown,
+            (Some("expired_token"), _) | (None, Some(410)) => {
+                PollError::Failed(DeviceAuthError::Expired)
+            }
+            (Some("access_denied"), _) | (None, Some(418)) => {
+                PollError::Failed(DeviceAuthError::Denied)
+            }
+            (None, Some(404)) => PollError::Failed(DeviceAuthError::InvalidCode),
+            (None, Some(409)) => PollError::Failed(DeviceAuthError::AlreadyUsed),
+            _ => PollError::Failed(DeviceAuthError::Http(error)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct DeviceCodeRequest<'a> {
+    client_id: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct DeviceTokenRequest<'a> {
+    code: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct RefreshTokenRequest<'a> {
+    refresh_token: &'a str,
+    client_id: &'a str,
+    client_secret: &'a str,
+    redirect_uri: &'a str,
+    grant_type: &'a str,
+}
+
+impl TraktClient {
+    /// Starts the device flow. The returned codes must be shown to the user
+    /// before polling for the token.
+    pub async fn start_device_auth(client_id: &str) -> Result<DeviceCode, Error> {
+        auth_client()?
+            .post("/oauth/device/code")
+            .json(&DeviceCodeRequest { client_id })
+            .send::<DeviceCode>()
+            .await
+    }
+
+    /// Polls until the user approves or denies the device code, the code
+    /// expires, or `cancel` completes. Pass `std::future::pending()` to wait
+    /// without a cancellation signal.
+    pub async fn poll_device_token<C>(
+        client_id: &str,
+        client_secret: &str,
+        code: &DeviceCode,
+        cancel: C,
+    ) -> Result<TraktOAuthToken, DeviceAuthError>
+    where
+        C: Future<Output = ()>,
+    {
+        tokio::pin!(cancel);
+        let client = auth_client()?;
+        let deadline = Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = Duration::from_secs(code.interval.max(1));
+        loop {
+            tokio::select! {
+                _ = &mut cancel => return Err(DeviceAuthError::Cancelled),
+                _ = tokio::time::sleep(interval) => {}
+            }
+            if Instant::now() >= deadline {
+                return Err(DeviceAuthError::Expired);
+            }
+
+            let result = client
+                .post("/oauth/device/token")
+                .json(&DeviceTokenRequest {
+                    code: &code.device_code,
+                    client_id,
+                    client_secret,
+                })
+                .send::<TraktOAuthToken>()
+                .await;
+            match result.map_err(PollError::from) {
+                Ok(token) => return Ok(token),
+                Err(PollError::Pending) => {}
+                Err(PollError::SlowDown) => {
+                    interval += SLOW_DOWN_STEP;
+                    log::debug!("Trakt asked to slow down, polling every {interval:?}");
+                }
+                Err(PollError::Failed(e)) => return Err(e),
+            }
+        }
+    }
+
+    /// Exchanges a refresh token for a new access and refresh token pair.
+    pub async fn refresh_token(
+        client_id: &str,
+        client_secret: &str,
+        refresh_token: &str,
+    ) -> Result<TraktOAuthToken, Error> {
+        auth_client()?
+            .post("/oauth/token")
+            .json(&RefreshTokenRequest {
+                refresh_token,
+                client_id,
+                client_secret,
+                redirect_uri: "urn:ietf:wg:oauth:2.0:oob",
+                grant_type: "refresh_token",
+            })
+            .send::<TraktOAuthToken>()
+            .await
+    }
+}
+
+/// Client for the OAuth endpoints. Polling has its own cadence, so failed
+/// calls are never retried.
+fn auth_client() -> Result<HttpClient, Error> {
+    Ok(HttpClient::new(TRAKT_URL, None)?.with_retry_policy(RetryPolicy::none()))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TokenStoreError {
+    #[error("failed to access token file {path}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("token file {path} is not valid")]
+    Json {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// Persists a `TraktOAuthToken` as JSON in a local file so it survives
+/// restarts.
+#[derive(Debug, Clone)]
+pub struct TokenStore {
+    path: PathBuf,
+}
+
+impl TokenStore {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+        }
+    }
+
+    /// Loads the stored token, or `None` if nothing has been stored yet.
+    pub fn load(&self) -> Result<Option<TraktOAuthToken>, TokenStoreError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(source) => return Err(self.io_error(source)),
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|source| TokenStoreError::Json {
+                path: self.path.clone(),
+                source,
+            })
+    }
+
+    /// Writes the token through a temporary file so a crash never leaves a
+    /// truncated token behind. On Unix the file is readable by its owner only.
+    pub fn save(&self, token: &TraktOAuthToken) -> Result<(), TokenStoreError> {
+        let text = serde_json::to_string_pretty(token).map_err(|source| TokenStoreError::Json {
+            path: self.path.clone(),
+            source,
+        })?;
+        let tmp = self.path.with_extension("tmp");
+        // A leftover from an earlier crash would keep its old permissions.
+        match std::fs::remove_file(&tmp) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(self.io_error(e)),
+            _ => {}
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        options
+            .open(&tmp)
+            .and_then(|mut file| {
+                file.write_all(text.as_bytes())?;
+                file.sync_all()
+            })
+            .map_err(|source| self.io_error(source))?;
+        std::fs::rename(&tmp, &self.path).map_err(|source| self.io_error(source))
+    }
+
+    fn io_error(&self, source: std::io::Error) -> TokenStoreError {
+        TokenStoreError::Io {
+            path: self.path.clone(),
+            source,
+        }
+    }
+}
@@ -1,238 +1,488 @@
 use log;
-use std::collections::HashMap;
 use std::time::Duration;
 
-use mini_moka::unsync::Cache;
-use oauth2;
-use oauth2::{DeviceAuthorizationResponse, ExtraDeviceAuthorizationFields, TokenResponse};
+use chrono::NaiveDate;
+use futures::{Stream, TryStreamExt};
+use mini_moka::sync::Cache;
 use reqwest;
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::base::{header_value, HttpClient};
+use crate::cache::{CacheKind, MetadataCache};
+pub use crate::media::MediaType;
+use crate::pagination::{page_paginated, Page};
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::trakt::structs::{
+    CalendarShow, Episode, IdLookup, LikedList, ListItem, Movie, Scrobble, ScrobbleResponse,
+    SearchResult, SearchResultItem, SearchResultMovie, SearchResultShow, Season, Show, SortOrder,
+    SyncItems, SyncResponse, TraktList, WatchList, WatchListItem, WatchListSort, WatchListType,
+};
+use crate::Error;
+
+const PAGE_SIZE: u64 = 100;
+/// How long the seasons of a show are reused from the metadata cache.
+const SEASONS_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Sizes and lifetimes of the cache behind `TraktClient::search`.
+#[derive(Debug, Clone)]
+pub struct SearchCacheConfig {
+    /// Maximum number of cached lookups, found or not.
+    pub capacity: u64,
+    /// How long a lookup that found something is reused.
+    pub ttl: Duration,
+    /// How long a lookup that found nothing is reused. Kept short since the
+    /// title may be added to Trakt soon.
+    pub negative_ttl: Duration,
+}
 
-use crate::base::HttpClient;
-use crate::trakt::client::client_utils::async_http_client;
-use crate::trakt::structs::{SearchResult, WatchList};
+impl Default for SearchCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: Duration::from_secs(60 * 60),
+            negative_ttl: Duration::from_secs(5 * 60),
+        }
+    }
+}
 
+#[derive(Clone)]
 pub struct TraktClient {
     client: HttpClient,
-    cache: Cache<String, Result<SearchResult, reqwest::Error>>,
-}
-
-#[derive(Debug)]
-pub enum MediaType {
-    Movie,
-    Show,
+    found: Cache<String, SearchResult>,
+    not_found: Cache<String, ()>,
+    metadata_cache: Option<MetadataCache>,
 }
 
-pub struct TraktOAuthToken {
-    pub access_token: String,
-    pub refresh_token: String,
-    pub expires_in: Option<Duration>,
-}
+impl TraktClient {
+    pub fn new(token: &str, client_id: &str) -> Result<Self, Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert("Authorization", header_value(&format!("Bearer {token}"))?);
+        headers.insert("trakt-api-key", header_value(client_id)?);
+        headers.insert(
+            "trakt-api-version",
+            reqwest::header::HeaderValue::from_static("2"),
+        );
+        let config = SearchCacheConfig::default();
+        Ok(Self {
+            client: HttpClient::new("https://api.trakt.tv", Some(headers))?
+                .with_rate_limiter(RateLimiter::trakt()),
+            found: cache(config.capacity, config.ttl),
+            not_found: cache(config.capacity, config.negative_ttl),
+            metadata_cache: None,
+        })
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct StoringFields(HashMap<String, serde_json::Value>);
+    /// Keeps id mappings and metadata in `cache` as well, so they survive
+    /// restarts. Seasons grow as shows air and are only reused for
+    /// `SEASONS_TTL`.
+    pub fn with_metadata_cache(mut self, cache: MetadataCache) -> Self {
+        self.metadata_cache = Some(cache);
+        self
+    }
 
-impl ExtraDeviceAuthorizationFields for StoringFields {}
-type StoringDeviceAuthorizationResponse = DeviceAuthorizationResponse<StoringFields>;
+    /// Replaces the search cache, dropping everything cached so far.
+    pub fn with_search_cache(mut self, config: SearchCacheConfig) -> Self {
+        self.found = cache(config.capacity, config.ttl);
+        self.not_found = cache(config.capacity, config.negative_ttl);
+        self
+    }
 
-impl TraktClient {
-    pub fn new(token: &str, client_id: &str) -> Self {
-        let mut headers = reqwest::header::HeaderMap::new();
-        headers.insert("Authorization", format!("Bearer {token}").parse().unwrap());
-        headers.insert("trakt-api-key", client_id.parse().unwrap());
-        headers.insert("trakt-api-version", "2".parse().unwrap());
-        Self {
-            client: HttpClient::new("https://api.trakt.tv", Some(headers)).unwrap(),
-            cache: Cache::builder()
-                .time_to_live(Duration::from_secs(5 * 60))
-                .build(),
-        }
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.client = self.client.with_retry_policy(policy);
+        self
     }
 
-    pub async fn oauth2(client_id: &str, client_secret: &str) -> TraktOAuthToken {
-        let trakt_client_id = oauth2::ClientId::new(client_id.to_string());
-        let trakt_client_secret = oauth2::ClientSecret::new(client_secret.to_string());
-        let auth_url = oauth2::AuthUrl::new("https://api.trakt.tv/oauth/device/code".to_string())
-            .expect("Invalid authorization endpoint URL");
-        let token_url =
-            oauth2::TokenUrl::new("https://api.trakt.tv/oauth/device/token".to_string())
-                .expect("Invalid token endpoint URL");
-        let device_auth_url = oauth2::DeviceAuthorizationUrl::new(
-            "https://api.trakt.tv/oauth/device/code".to_string(),
-        )
-        .expect("Invalid device authorization endpoint URL");
-
-        let device_client = oauth2::basic::BasicClient::new(
-            trakt_client_id,
-            Some(trakt_client_secret),
-            auth_url,
-            Some(token_url),
-        )
-        .set_device_authorization_url(device_auth_url)
-        .set_auth_type(oauth2::AuthType::RequestBody);
-
-        let details: StoringDeviceAuthorizationResponse = device_client
-            .exchange_device_code()
-            .unwrap()
-            .request_async(&oauth2::reqwest::async_http_client)
-            .await
-            .expect("Failed to request codes from device auth endpoint");
+    pub fn with_rate_limiter(mut self, limiter: RateLimiter) -> Self {
+        self.client = self.client.with_rate_limiter(limiter);
+        self
+    }
 
-        println!("{details:?}");
-        println!("{:?}", details.device_code().secret());
-        println!("{:?}", details.interval());
-        println!("{:?}", details.expires_in());
+    /// Swaps the access token used for every subsequent request without
+    /// recreating the client.
+    pub fn set_access_token(&self, token: &str) -> Result<(), Error> {
+        let value = header_value(&format!("Bearer {token}"))?;
+        self.client
+            .set_header(reqwest::header::AUTHORIZATION, value);
+        Ok(())
+    }
 
-        println!(
-            "Open this URL in your browser:\n{:?}\nand enter the code: {}",
-            details.verification_uri(),
-            details.user_code().secret(),
+    /// Streams the watchlist items of the given kinds, or of every kind if
+    /// `types` is empty, in the requested order. Further pages are fetched as
+    /// the stream is consumed.
+    pub fn watchlist<'a>(
+        &'a self,
+        types: &[WatchListType],
+        sort: WatchListSort,
+        order: SortOrder,
+    ) -> impl Stream<Item = Result<WatchListItem, Error>> + 'a {
+        let types = if types.is_empty() {
+            "all".to_string()
+        } else {
+            types
+                .iter()
+                .map(WatchListType::as_str)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let path = format!(
+            "/sync/watchlist/{types}/{}/{}",
+            sort.as_str(),
+            order.as_str()
         );
+        self.paginated(path)
+    }
 
-        let token = device_client
-            .exchange_device_access_token(&details)
-            .add_extra_param("code", details.device_code().secret())
-            .request_async(&async_http_client, tokio::time::sleep, None)
+    pub async fn get_watchlist(
+        &self,
+        types: &[WatchListType],
+        sort: WatchListSort,
+        order: SortOrder,
+    ) -> Result<WatchList, Error> {
+        let watchlist = self.watchlist(types, sort, order).try_collect().await?;
+        Ok(WatchList { watchlist })
+    }
+
+    pub async fn get_user_lists(&self, user: &str) -> Result<Vec<TraktList>, Error> {
+        self.client
+            .get(format!("/users/{user}/lists"))
+            .send::<Vec<TraktList>>()
             .await
-            .expect("Failed to get token");
+    }
 
-        TraktOAuthToken {
-            access_token: token.access_token().secret().to_string(),
-            refresh_token: token.refresh_token().unwrap().secret().to_string(),
-            expires_in: token.expires_in(),
-        }
+    /// Streams the items of a user's custom list. `list` is the list's Trakt
+    /// id or slug; public lists of any user can be read.
+    pub fn list_items(
+        &self,
+        user: &str,
+        list: &str,
+    ) -> impl Stream<Item = Result<ListItem, Error>> + '_ {
+        self.paginated(format!("/users/{user}/lists/{list}/items"))
     }
 
-    pub async fn get_watchlist(&self) -> Result<WatchList, reqwest::Error> {
+    pub async fn get_list_items(&self, user: &str, list: &str) -> Result<Vec<ListItem>, Error> {
+        self.list_items(user, list).try_collect().await
+    }
+
+    /// Streams the lists the authenticated user has liked.
+    pub fn liked_lists(&self) -> impl Stream<Item = Result<LikedList, Error>> + '_ {
+        self.paginated("/users/likes/lists".to_string())
+    }
+
+    pub async fn get_liked_lists(&self) -> Result<Vec<LikedList>, Error> {
+        self.liked_lists().try_collect().await
+    }
+
+    /// Episodes of the shows the authenticated user follows airing in the
+    /// `days` days from `start`.
+    pub async fn get_my_calendar_shows(
+        &self,
+        start: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<CalendarShow>, Error> {
         self.client
-            .request::<WatchList>(
-                reqwest::Method::GET,
-                "/sync/watchlist/movies,shows",
-                None,
-                None,
-                None,
-            )
+            .get(format!("/calendars/my/shows/{start}/{days}"))
+            .send::<Vec<CalendarShow>>()
             .await
     }
 
-    pub async fn search(&mut self, id: u64, kind: &str) -> &Result<SearchResult, reqwest::Error> {
-        let search_kind = if kind == "movie" {
-            "movie"
-        } else if kind == "tv" {
-            "show"
-        } else {
-            "movie"
-        };
+    /// Episodes of every show airing in the `days` days from `start`.
+    pub async fn get_all_calendar_shows(
+        &self,
+        start: NaiveDate,
+        days: u32,
+    ) -> Result<Vec<CalendarShow>, Error> {
+        self.client
+            .get(format!("/calendars/all/shows/{start}/{days}"))
+            .send::<Vec<CalendarShow>>()
+            .await
+    }
 
-        let cache_key = format!("{id}-{search_kind}");
-        if self.cache.contains_key(&cache_key) {
-            log::debug!("Using cached search result for {cache_key}");
-            return self.cache.get(&cache_key).unwrap();
+    /// Every season of a show with its episodes and air dates. `id` is the
+    /// show's Trakt id, slug or IMDb id.
+    pub async fn get_show_seasons(&self, id: &str) -> Result<Vec<Season>, Error> {
+        let key = format!("trakt:seasons:{id}");
+        if let Some(seasons) = self.stored_fresh(CacheKind::Metadata, &key, SEASONS_TTL) {
+            return Ok(seasons);
         }
+        let seasons = self
+            .client
+            .get(format!("/shows/{id}/seasons"))
+            .query(&[("extended", "full,episodes")])
+            .send::<Vec<Season>>()
+            .await?;
+        self.store(CacheKind::Metadata, &key, &seasons);
+        Ok(seasons)
+    }
 
-        let params = HashMap::from([("type".to_string(), search_kind.to_string())]);
-        let result = self
+    /// The episodes of one season of a show, with air dates.
+    pub async fn get_season_episodes(&self, id: &str, season: u32) -> Result<Vec<Episode>, Error> {
+        self.client
+            .get(format!("/shows/{id}/seasons/{season}"))
+            .query(&[("extended", "full")])
+            .send::<Vec<Episode>>()
+            .await
+    }
+
+    /// A movie with its release date. `id` is the movie's Trakt id, slug or
+    /// IMDb id.
+    pub async fn get_movie(&self, id: &str) -> Result<Movie, Error> {
+        let key = format!("trakt:movie:{id}");
+        if let Some(movie) = self.stored(CacheKind::Metadata, &key) {
+            return Ok(movie);
+        }
+        let movie = self
             .client
-            .request::<SearchResult>(
-                reqwest::Method::GET,
-                format!("/search/tmdb/{id}").as_str(),
-                Some(params),
-                None,
-                None,
-            )
-            .await;
-
-        self.cache.insert(cache_key.clone(), result);
-        self.cache.get(&cache_key).unwrap()
+            .get(format!("/movies/{id}"))
+            .query(&[("extended", "full")])
+            .send::<Movie>()
+            .await?;
+        self.store(CacheKind::Metadata, &key, &movie);
+        Ok(movie)
     }
-}
 
-pub mod client_utils {
-    use log;
-    use std::str::FromStr;
+    /// Adds items to the authenticated user's collection, along with what
+    /// the collected copies look like.
+    pub async fn add_to_collection(&self, items: &SyncItems) -> Result<SyncResponse, Error> {
+        self.client
+            .post("/sync/collection")
+            .json(items)
+            .send::<SyncResponse>()
+            .await
+    }
 
-    use oauth2;
-    use thiserror;
+    pub async fn remove_from_collection(&self, items: &SyncItems) -> Result<SyncResponse, Error> {
+        self.client
+            .post("/sync/collection/remove")
+            .json(items)
+            .send::<SyncResponse>()
+            .await
+    }
 
-    #[derive(Debug, thiserror::Error)]
-    pub enum Error<T>
-    where
-        T: std::error::Error + 'static,
-    {
-        /// Error returned by reqwest crate.
-        #[error("request failed")]
-        Reqwest(#[source] T),
-        /// Non-reqwest HTTP error.
-        #[error("HTTP error")]
-        Http(#[source] oauth2::http::Error),
-        /// I/O error.
-        #[error("I/O error")]
-        Io(#[source] std::io::Error),
-        /// Other error.
-        #[error("Other error: {}", _0)]
-        Other(String),
-    }
-
-    pub async fn async_http_client(
-        request: oauth2::HttpRequest,
-    ) -> Result<oauth2::HttpResponse, Error<reqwest::Error>> {
-        let client = {
-            let builder = reqwest::Client::builder();
-
-            // Following redirects opens the client up to SSRF vulnerabilities.
-            // but this is not possible to prevent on wasm targets
-            #[cfg(not(target_arch = "wasm32"))]
-            let builder = builder.redirect(reqwest::redirect::Policy::none());
-
-            builder.build().map_err(Error::Reqwest)?
-        };
+    pub async fn remove_from_watchlist(&self, items: &SyncItems) -> Result<SyncResponse, Error> {
+        self.client
+            .post("/sync/watchlist/remove")
+            .json(items)
+            .send::<SyncResponse>()
+            .await
+    }
+
+    /// Tells Trakt playback started or resumed.
+    pub async fn scrobble_start(&self, scrobble: &Scrobble) -> Result<ScrobbleResponse, Error> {
+        self.scrobble("start", scrobble).await
+    }
+
+    pub async fn scrobble_pause(&self, scrobble: &Scrobble) -> Result<ScrobbleResponse, Error> {
+        self.scrobble("pause", scrobble).await
+    }
+
+    /// Tells Trakt playback ended. Past 80% progress the item is added to the
+    /// watch history; Trakt answers 409 if it was scrobbled moments ago.
+    pub async fn scrobble_stop(&self, scrobble: &Scrobble) -> Result<ScrobbleResponse, Error> {
+        self.scrobble("stop", scrobble).await
+    }
+
+    async fn scrobble(&self, action: &str, scrobble: &Scrobble) -> Result<ScrobbleResponse, Error> {
+        self.client
+            .post(format!("/scrobble/{action}"))
+            .json(scrobble)
+            .send::<ScrobbleResponse>()
+            .await
+    }
 
-        let mut request_builder = client
-            .request(reqwest::Method::POST, request.url.as_str())
-            .body(request.body.clone());
-        for (name, value) in &request.headers {
-            request_builder = request_builder.header(name.as_str(), value.as_bytes());
+    /// Looks a title up by one of its ids, restricted to `media_type` if
+    /// given. Results are cached, failed requests are not.
+    pub async fn search(
+        &self,
+        lookup: &IdLookup,
+        media_type: Option<MediaType>,
+    ) -> Result<SearchResult, Error> {
+        let kind = media_type.map(search_type).unwrap_or("any");
+        let cache_key = format!("{}-{}-{kind}", lookup.id_type(), lookup.id());
+        if let Some(result) = self.found.get(&cache_key) {
+            log::debug!("Using cached search result for {cache_key}");
+            return Ok(result);
         }
-        let request_ = request_builder.build().map_err(Error::Reqwest)?;
-        let response = client.execute(request_).await.map_err(Error::Reqwest)?;
-
-        log::debug!(
-            "POST {url} - {code} -- {body}",
-            url = request.url,
-            body = String::from_utf8_lossy(&request.body).to_string(),
-            code = response.status(),
-        );
+        if self.not_found.contains_key(&cache_key) {
+            log::debug!("Using cached empty search result for {cache_key}");
+            return Ok(SearchResult {
+                searchresult: Vec::new(),
+            });
+        }
+        let stored_key = format!("trakt:search:{cache_key}");
+        if let Some(result) = self.stored::<SearchResult>(CacheKind::IdMapping, &stored_key) {
+            self.found.insert(cache_key, result.clone());
+            return Ok(result);
+        }
+        if self
+            .stored::<()>(CacheKind::NotFound, &stored_key)
+            .is_some()
+        {
+            self.not_found.insert(cache_key, ());
+            return Ok(SearchResult {
+                searchresult: Vec::new(),
+            });
+        }
+
+        let result = match lookup {
+            IdLookup::Slug(slug) => self.lookup_slug(slug, media_type).await?,
+            _ => {
+                let request =
+                    self.client
+                        .get(format!("/search/{}/{}", lookup.id_type(), lookup.id()));
+                match media_type {
+                    Some(media_type) => request.query(&[("type", search_type(media_type))]),
+                    None => request,
+                }
+                .send::<SearchResult>()
+                .await?
+            }
+        };
 
-        let resp_status_code = u16::from(response.status());
-        let status_code = oauth2::http::StatusCode::from_u16(resp_status_code)
-            .map_err(|err| Error::Http(err.into()))?;
-
-        let mut headers = oauth2::http::HeaderMap::new();
-        let resp_headers = response.headers();
-        for (name, value) in resp_headers.iter() {
-            let name = name.as_str();
-            let header_name = oauth2::http::header::HeaderName::from_str(name).unwrap();
-            let value = value.to_str().unwrap().to_owned();
-            let header_value = oauth2::http::HeaderValue::from_str(&value).unwrap();
-            headers.append(header_name, header_value);
+        if result.searchresult.is_empty() {
+            self.store(CacheKind::NotFound, &stored_key, &());
+            self.not_found.insert(cache_key, ());
+        } else {
+            self.store(CacheKind::IdMapping, &stored_key, &result);
+            self.found.insert(cache_key, result.clone());
         }
+        Ok(result)
+    }
 
-        let chunks = response.bytes().await.map_err(Error::Reqwest)?;
-        let body = if chunks.to_vec().is_empty() {
-            // This is a utf-8 encoding for {"error": "authorization_pending"}
-            vec![
-                123, 034, 101, 114, 114, 111, 114, 034, 058, 032, 034, 097, 117, 116, 104, 111,
-                114, 105, 122, 097, 116, 105, 111, 110, 095, 112, 101, 110, 100, 105, 110, 103,
-                034, 125,
-            ]
+    /// Searches titles of the given kinds, or movies and shows if `types` is
+    /// empty, optionally only those released in `year`.
+    pub async fn search_text(
+        &self,
+        query: &str,
+        types: &[MediaType],
+        year: Option<u32>,
+    ) -> Result<SearchResult, Error> {
+        let types = if types.is_empty() {
+            &[MediaType::Movie, MediaType::Show][..]
         } else {
-            chunks.to_vec()
+            types
         };
+        let types = types
+            .iter()
+            .map(|media_type| search_type(*media_type))
+            .collect::<Vec<_>>()
+            .join(",");
+        let mut request = self
+            .client
+            .get(format!("/search/{types}"))
+            .query(&[("query", query)]);
+        if let Some(year) = year {
+            request = request.query(&[("years", year)]);
+        }
+        request.send::<SearchResult>().await
+    }
+
+    /// Slugs are not searchable, so the title is fetched directly. A slug
+    /// that matches nothing gives an empty result like a search would.
+    async fn lookup_slug(
+        &self,
+        slug: &str,
+        media_type: Option<MediaType>,
+    ) -> Result<SearchResult, Error> {
+        let types = match media_type {
+            Some(media_type) => vec![media_type],
+            None => vec![MediaType::Movie, MediaType::Show],
+        };
+        let mut searchresult = Vec::new();
+        for media_type in types {
+            let item = match media_type {
+                MediaType::Movie => self
+                    .client
+                    .get(format!("/movies/{slug}"))
+                    .send::<Movie>()
+                    .await
+                    .map(|movie| SearchResultItem::Movie(SearchResultMovie { score: 0.0, movie })),
+                MediaType::Show => self
+                    .client
+                    .get(format!("/shows/{slug}"))
+                    .send::<Show>()
+                    .await
+                    .map(|show| SearchResultItem::Show(SearchResultShow { score: 0.0, show })),
+            };
+            match item {
+                Ok(item) => searchresult.push(item),
+                Err(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(SearchResult { searchresult })
+    }
+
+    /// Reads from the persistent cache, treating a broken cache as a miss.
+    fn stored<T: DeserializeOwned>(&self, kind: CacheKind, key: &str) -> Option<T> {
+        let cache = self.metadata_cache.as_ref()?;
+        cache.get(kind, key).unwrap_or_else(|e| {
+            log::warn!("Ignoring metadata cache entry {key}: {e}");
+            None
+        })
+    }
 
-        Ok(oauth2::HttpResponse {
-            status_code,
-            headers,
-            body,
+    fn stored_fresh<T: DeserializeOwned>(
+        &self,
+        kind: CacheKind,
+        key: &str,
+        max_age: Duration,
+    ) -> Option<T> {
+        let cache = self.metadata_cache.as_ref()?;
+        cache.get_fresh(kind, key, max_age).unwrap_or_else(|e| {
+            log::warn!("Ignoring metadata cache entry {key}: {e}");
+            None
         })
     }
+
+    fn store<T: Serialize>(&self, kind: CacheKind, key: &str, value: &T) {
+        if let Some(cache) = &self.metadata_cache {
+            if let Err(e) = cache.put(kind, key, value) {
+                log::warn!("Failed to cache {key}: {e}");
+            }
+        }
+    }
+
+    /// Streams a list endpoint paginated through Trakt's `page`/`limit`
+    /// parameters and `X-Pagination-*` headers.
+    fn paginated<T>(&self, path: String) -> impl Stream<Item = Result<T, Error>> + '_
+    where
+        T: for<'de> serde::Deserialize<'de> + 'static,
+    {
+        page_paginated(PAGE_SIZE, move |page, limit| {
+            let path = path.clone();
+            async move {
+                let response = self
+                    .client
+                    .get(path)
+                    .query(&[("page", page), ("limit", limit)])
+                    .send_response::<Vec<T>>()
+                    .await?;
+                Ok(Page {
+                    total: response.header_as("x-pagination-item-count"),
+                    page_count: response.header_as("x-pagination-page-count"),
+                    items: response.body,
+                })
+            }
+        })
+    }
+}
+
+fn cache<V>(capacity: u64, ttl: Duration) -> Cache<String, V>
+where
+    V: Clone + Send + Sync + 'static,
+{
+    Cache::builder()
+        .max_capacity(capacity)
+        .time_to_live(ttl)
+        .build()
+}
+
+/// The `type` Trakt's search endpoints use for a media type.
+fn search_type(media_type: MediaType) -> &'static str {
+    match media_type {
+        MediaType::Movie => "movie",
+        MediaType::Show => "show",
+    }
 }
@@ -1,5 +1,8 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::media::{MediaIdentity, MediaIds, MediaType};
+
 #[derive(Deserialize, Debug, Serialize)]
 #[serde(transparent)]
 pub struct WatchList {
@@ -7,19 +10,274 @@ pub struct WatchList {
 }
 
 #[derive(Deserialize, Debug, Serialize)]
+#[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
-pub struct WatchListItem {
-    pub r#type: String,
+pub enum WatchListItem {
+    Movie(WatchListMovie),
+    Show(WatchListShow),
+    Season(WatchListSeason),
+    Episode(WatchListEpisode),
+}
+
+impl WatchListItem {
+    pub fn rank(&self) -> u32 {
+        match self {
+            WatchListItem::Movie(item) => item.rank,
+            WatchListItem::Show(item) => item.rank,
+            WatchListItem::Season(item) => item.rank,
+            WatchListItem::Episode(item) => item.rank,
+        }
+    }
+
+    pub fn listed_at(&self) -> &str {
+        match self {
+            WatchListItem::Movie(item) => &item.listed_at,
+            WatchListItem::Show(item) => &item.listed_at,
+            WatchListItem::Season(item) => &item.listed_at,
+            WatchListItem::Episode(item) => &item.listed_at,
+        }
+    }
+
+    /// The media this item refers to.
+    pub fn identity(&self) -> MediaIdentity {
+        match self {
+            WatchListItem::Movie(item) => item.movie.identity(),
+            WatchListItem::Show(item) => item.show.identity(),
+            WatchListItem::Season(item) => item.identity(),
+            WatchListItem::Episode(item) => item.identity(),
+        }
+    }
+
+    pub fn notes(&self) -> Option<&str> {
+        match self {
+            WatchListItem::Movie(item) => item.notes.as_deref(),
+            WatchListItem::Show(item) => item.notes.as_deref(),
+            WatchListItem::Season(item) => item.notes.as_deref(),
+            WatchListItem::Episode(item) => item.notes.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchListMovie {
+    pub rank: u32,
+    pub id: u64,
+    pub listed_at: String,
+    pub notes: Option<String>,
+    pub movie: Movie,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchListShow {
+    pub rank: u32,
+    pub id: u64,
+    pub listed_at: String,
+    pub notes: Option<String>,
+    pub show: Show,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchListSeason {
+    pub rank: u32,
+    pub id: u64,
+    pub listed_at: String,
+    pub notes: Option<String>,
+    pub season: Season,
+    pub show: Show,
+}
+
+impl WatchListSeason {
+    /// The season, identified through its show.
+    pub fn identity(&self) -> MediaIdentity {
+        MediaIdentity {
+            season: Some(self.season.number),
+            ..self.show.identity()
+        }
+    }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchListEpisode {
+    pub rank: u32,
+    pub id: u64,
+    pub listed_at: String,
+    pub notes: Option<String>,
+    pub episode: Episode,
+    pub show: Show,
+}
+
+impl WatchListEpisode {
+    /// The episode, identified through its show.
+    pub fn identity(&self) -> MediaIdentity {
+        MediaIdentity {
+            season: Some(self.episode.season),
+            episode: Some(self.episode.number),
+            ..self.show.identity()
+        }
+    }
+}
+
+/// An entry of a custom list. Apart from people, list entries have the same
+/// shape as watchlist entries.
 #[derive(Deserialize, Debug, Serialize)]
-#[serde(transparent)]
+#[serde(tag = "type")]
+#[serde(rename_all = "camelCase")]
+pub enum ListItem {
+    Movie(WatchListMovie),
+    Show(WatchListShow),
+    Season(WatchListSeason),
+    Episode(WatchListEpisode),
+    Person(ListPerson),
+}
+
+impl ListItem {
+    /// The media this entry refers to, or `None` for people.
+    pub fn identity(&self) -> Option<MediaIdentity> {
+        match self {
+            ListItem::Movie(item) => Some(item.movie.identity()),
+            ListItem::Show(item) => Some(item.show.identity()),
+            ListItem::Season(item) => Some(item.identity()),
+            ListItem::Episode(item) => Some(item.identity()),
+            ListItem::Person(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPerson {
+    pub rank: u32,
+    pub id: u64,
+    pub listed_at: String,
+    pub notes: Option<String>,
+    pub person: Person,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraktList {
+    pub name: String,
+    pub description: Option<String>,
+    pub privacy: String,
+    pub display_numbers: bool,
+    pub allow_comments: bool,
+    pub sort_by: String,
+    pub sort_how: String,
+    pub created_at: String,
+    pub updated_at: String,
+    pub item_count: u64,
+    pub comment_count: u64,
+    pub likes: u64,
+    pub ids: ListIds,
+    pub user: Option<User>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListIds {
+    pub trakt: u64,
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub username: String,
+    pub private: bool,
+    pub name: Option<String>,
+    pub vip: Option<bool>,
+    pub ids: UserIds,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserIds {
+    pub slug: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LikedList {
+    pub liked_at: String,
+    pub list: TraktList,
+}
 
+/// An episode airing, as listed by the calendar endpoints.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarShow {
+    pub first_aired: DateTime<Utc>,
+    pub episode: Episode,
+    pub show: Show,
+}
+
+impl CalendarShow {
+    /// The episode, identified through its show.
+    pub fn identity(&self) -> MediaIdentity {
+        MediaIdentity {
+            season: Some(self.episode.season),
+            episode: Some(self.episode.number),
+            ..self.show.identity()
+        }
+    }
+}
+
+/// Kinds of items a watchlist request can be restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchListType {
+    Movies,
+    Shows,
+    Seasons,
+    Episodes,
+}
+
+impl WatchListType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchListType::Movies => "movies",
+            WatchListType::Shows => "shows",
+            WatchListType::Seasons => "seasons",
+            WatchListType::Episodes => "episodes",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WatchListSort {
+    #[default]
+    Rank,
+    Added,
+    Released,
+    Title,
+}
+
+impl WatchListSort {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchListSort::Rank => "rank",
+            WatchListSort::Added => "added",
+            WatchListSort::Released => "released",
+            WatchListSort::Title => "title",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "asc",
+            SortOrder::Desc => "desc",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Serialize)]
+#[serde(transparent)]
 pub struct SearchResult {
     pub searchresult: Vec<SearchResultItem>,
 }
 
-#[derive(Deserialize, Debug, Serialize)]
+#[derive(Deserialize, Debug, Clone, Serialize)]
 #[serde(tag = "type")]
 #[serde(rename_all = "camelCase")]
 pub enum SearchResultItem {
@@ -29,61 +287,447 @@ pub enum SearchResultItem {
     Person(SearchResultPerson),
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl SearchResultItem {
+    /// The movie or show found, `None` for episodes and people.
+    pub fn identity(&self) -> Option<MediaIdentity> {
+        match self {
+            SearchResultItem::Movie(movie) => Some(movie.movie.identity()),
+            SearchResultItem::Show(show) => Some(show.show.identity()),
+            SearchResultItem::Episode(_) | SearchResultItem::Person(_) => None,
+        }
+    }
+}
+
+/// An id to look a title up by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IdLookup {
+    Tmdb(u64),
+    Imdb(String),
+    Tvdb(u64),
+    Trakt(u64),
+    Slug(String),
+}
+
+impl IdLookup {
+    /// The `id_type` segment of `/search/{id_type}/{id}`. Slugs cannot be
+    /// searched for and are looked up directly instead.
+    pub fn id_type(&self) -> &'static str {
+        match self {
+            IdLookup::Tmdb(_) => "tmdb",
+            IdLookup::Imdb(_) => "imdb",
+            IdLookup::Tvdb(_) => "tvdb",
+            IdLookup::Trakt(_) => "trakt",
+            IdLookup::Slug(_) => "slug",
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            IdLookup::Tmdb(id) | IdLookup::Tvdb(id) | IdLookup::Trakt(id) => id.to_string(),
+            IdLookup::Imdb(id) | IdLookup::Slug(id) => id.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResultMovie {
     pub score: f32,
     pub movie: Movie,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Movie {
     pub title: String,
-    pub year: u32,
+    pub year: Option<u32>,
     pub ids: IDs,
+    /// Only present in extended responses.
+    pub released: Option<NaiveDate>,
+    pub runtime: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Movie {
+    pub fn identity(&self) -> MediaIdentity {
+        MediaIdentity {
+            title: Some(self.title.clone()),
+            year: self.year,
+            ..MediaIdentity::new(MediaType::Movie, MediaIds::from(&self.ids))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResultShow {
     pub score: f32,
     pub show: Show,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Show {
     pub title: String,
-    pub year: u32,
+    pub year: Option<u32>,
     pub ids: IDs,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Show {
+    pub fn identity(&self) -> MediaIdentity {
+        MediaIdentity {
+            title: Some(self.title.clone()),
+            year: self.year,
+            ..MediaIdentity::new(MediaType::Show, MediaIds::from(&self.ids))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResultEpisode {
     pub score: f32,
     pub episode: Episode,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Episode {
-    pub title: String,
+    pub season: u32,
+    pub number: u32,
+    pub title: Option<String>,
     pub ids: IDs,
+    /// Only present in extended responses, and missing for episodes without
+    /// an announced air date.
+    pub first_aired: Option<DateTime<Utc>>,
+    pub number_abs: Option<u32>,
+    pub runtime: Option<u32>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+impl Episode {
+    pub fn has_aired(&self, now: DateTime<Utc>) -> bool {
+        self.first_aired.is_some_and(|aired| aired <= now)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Season {
+    pub number: u32,
+    pub ids: IDs,
+    /// Only present in extended responses.
+    pub title: Option<String>,
+    pub first_aired: Option<DateTime<Utc>>,
+    pub episode_count: Option<u32>,
+    pub aired_episodes: Option<u32>,
+    /// Only filled in when requested with `extended=episodes`.
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchResultPerson {
     pub score: f32,
     pub person: Person,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Person {
     pub name: String,
     pub ids: IDs,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IDs {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trakt: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slug: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub imdb: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tmdb: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvdb: Option<u64>,
+}
+
+impl From<&MediaIds> for IDs {
+    fn from(ids: &MediaIds) -> Self {
+        IDs {
+            trakt: ids.trakt,
+            slug: None,
+            imdb: ids.imdb.clone(),
+            tmdb: ids.tmdb,
+            tvdb: ids.tvdb,
+        }
+    }
+}
+
+impl From<&IDs> for MediaIds {
+    fn from(ids: &IDs) -> Self {
+        MediaIds {
+            trakt: ids.trakt,
+            imdb: ids.imdb.clone(),
+            tmdb: ids.tmdb,
+            tvdb: ids.tvdb,
+        }
+    }
+}
+
+/// Items sent to the `/sync/*` endpoints. Shows may be narrowed down to
+/// individual seasons and episodes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncItems {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub movies: Vec<SyncMovie>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shows: Vec<SyncShow>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub episodes: Vec<SyncEpisode>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncMovie {
+    pub ids: IDs,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collected_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub metadata: CollectionMetadata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncShow {
+    pub ids: IDs,
+    /// Every season of the show is affected if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub seasons: Vec<SyncSeason>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSeason {
+    pub number: u32,
+    /// Every episode of the season is affected if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub episodes: Vec<SyncEpisode>,
+}
+
+/// An episode, either by number inside a [`SyncSeason`] or by `ids` on its
+/// own.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncEpisode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<IDs>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub collected_at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    pub metadata: CollectionMetadata,
+}
+
+/// What a collected copy looks like. Unknown properties are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollectionMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<CollectionMediaType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hdr: Option<Hdr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<Audio>,
+    /// Channel layout such as `5.1` or `7.1.4`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_channels: Option<String>,
+    #[serde(rename = "3d", skip_serializing_if = "Option::is_none")]
+    pub three_d: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CollectionMediaType {
+    Digital,
+    Bluray,
+    Hddvd,
+    Dvd,
+    Vcd,
+    Vhs,
+    Betamax,
+    Laserdisc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "uhd_4k")]
+    Uhd4k,
+    #[serde(rename = "hd_1080p")]
+    Hd1080p,
+    #[serde(rename = "hd_1080i")]
+    Hd1080i,
+    #[serde(rename = "hd_720p")]
+    Hd720p,
+    #[serde(rename = "sd_480p")]
+    Sd480p,
+    #[serde(rename = "sd_480i")]
+    Sd480i,
+    #[serde(rename = "sd_576p")]
+    Sd576p,
+    #[serde(rename = "sd_576i")]
+    Sd576i,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hdr {
+    DolbyVision,
+    Hdr10,
+    Hdr10Plus,
+    Hlg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Audio {
+    DolbyDigital,
+    DolbyDigitalPlus,
+    DolbyDigitalPlusAtmos,
+    DolbyTruehd,
+    DolbyAtmos,
+    DolbyPrologic,
+    Dts,
+    DtsMa,
+    DtsHr,
+    DtsX,
+    #[serde(rename = "auro_3d")]
+    Auro3d,
+    Mp3,
+    Mp2,
+    Aac,
+    Lpcm,
+    Ogg,
+    OggOpus,
+    Wma,
+    Flac,
+}
+
+/// Outcome of a `/sync/*` call. Only the counts relevant to the call are
+/// present.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SyncResponse {
+    pub added: Option<SyncCounts>,
+    pub updated: Option<SyncCounts>,
+    pub existing: Option<SyncCounts>,
+    pub deleted: Option<SyncCounts>,
+    #[serde(default)]
+    pub not_found: SyncNotFound,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncCounts {
+    pub movies: u64,
+    pub shows: u64,
+    pub seasons: u64,
+    pub episodes: u64,
+}
+
+/// Items of the request Trakt could not match.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SyncNotFound {
+    pub movies: Vec<serde_json::Value>,
+    pub shows: Vec<serde_json::Value>,
+    pub seasons: Vec<serde_json::Value>,
+    pub episodes: Vec<serde_json::Value>,
+}
+
+impl SyncNotFound {
+    pub fn is_empty(&self) -> bool {
+        self.movies.is_empty()
+            && self.shows.is_empty()
+            && self.seasons.is_empty()
+            && self.episodes.is_empty()
+    }
+}
+
+/// Body of the `/scrobble/*` endpoints: a movie, or an episode identified
+/// through its show or its own ids, and how far playback got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scrobble {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub movie: Option<ScrobbleMedia>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show: Option<ScrobbleMedia>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub episode: Option<ScrobbleEpisode>,
+    /// Percentage watched, from 0 to 100.
+    pub progress: f64,
+}
+
+impl Scrobble {
+    pub fn movie(ids: IDs, progress: f64) -> Self {
+        Self {
+            movie: Some(ScrobbleMedia { ids }),
+            show: None,
+            episode: None,
+            progress,
+        }
+    }
+
+    pub fn episode(show: IDs, season: u32, number: u32, progress: f64) -> Self {
+        Self {
+            movie: None,
+            show: Some(ScrobbleMedia { ids: show }),
+            episode: Some(ScrobbleEpisode {
+                season: Some(season),
+                number: Some(number),
+                ids: None,
+            }),
+            progress,
+        }
+    }
+
+    /// An episode known only by its own ids.
+    pub fn episode_ids(ids: IDs, progress: f64) -> Self {
+        Self {
+            movie: None,
+            show: None,
+            episode: Some(ScrobbleEpisode {
+                season: None,
+                number: None,
+                ids: Some(ids),
+            }),
+            progress,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrobbleMedia {
+    pub ids: IDs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrobbleEpisode {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ids: Option<IDs>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScrobbleAction {
+    Start,
+    Pause,
+    /// Playback stopped past 80% and was added to the history.
+    Scrobble,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScrobbleResponse {
+    pub id: u64,
+    pub action: ScrobbleAction,
+    pub progress: f64,
+    pub movie: Option<Movie>,
+    pub show: Option<Show>,
+    pub episode: Option<Episode>,
 }
@@ -0,0 +1,222 @@
+use clients::seerrs::structs::{
+    MediaStatus, MediaType, PageInfo, Permissions, RequestStatus, Requests, Season, SeasonRequest,
+    UserOrString, UserType,
+};
+
+/// A synthetic `/request` page, written by hand after Seerr's entity
+/// definitions rather than captured from a running server.
+const REQUESTS: &str = include_str!("fixtures/seerr/requests.json");
+
+fn requests() -> Requests {
+    serde_json::from_str(REQUESTS).expect("request page should deserialize")
+}
+
+#[test]
+fn deserializes_request_page() {
+    let requests = requests();
+    assert_eq!(requests.page_info.results, 3);
+    assert_eq!(requests.results.len(), 3);
+
+    let movie = &requests.results[0];
+    assert_eq!(movie.id, 412);
+    assert_eq!(movie.r#type, MediaType::Movie);
+    assert_eq!(movie.status, RequestStatus::Approved);
+    assert_eq!(movie.media.status, MediaStatus::Processing);
+    assert!(movie.seasons.is_empty());
+    assert!(matches!(movie.modified_by, Some(UserOrString::User(_))));
+}
+
+#[test]
+fn users_of_every_kind_deserialize() {
+    let requests = requests();
+
+    let jellyfin = &requests.results[0].requested_by;
+    assert_eq!(jellyfin.user_type, UserType::Jellyfin);
+    assert_eq!(jellyfin.jellyfin_username.as_deref(), Some("alice"));
+    assert_eq!(jellyfin.request_count, 312);
+
+    let plex = &requests.results[1].requested_by;
+    assert_eq!(plex.user_type, UserType::Plex);
+    assert_eq!(plex.plex_id, Some(48213377));
+    assert!(plex.jellyfin_user_id.is_none());
+
+    let local = &requests.results[2].requested_by;
+    assert_eq!(local.user_type, UserType::Local);
+    assert!(local.email.is_none());
+    assert_eq!(local.username.as_deref(), Some("carol"));
+}
+
+#[test]
+fn permissions_keep_every_bit() {
+    let requests = requests();
+    let permissions = requests.results[0].requested_by.permissions;
+    assert_eq!(permissions.bits(), 1082130464);
+    assert!(permissions.contains(Permissions::REQUEST | Permissions::AUTO_REQUEST));
+    assert!(permissions.contains(Permissions::VIEW_BLACKLIST));
+    assert!(!permissions.allows(Permissions::MANAGE_REQUESTS));
+
+    let admin = match &requests.results[0].modified_by {
+        Some(UserOrString::User(user)) => user.permissions,
+        other => panic!("expected a user, got {other:?}"),
+    };
+    assert!(admin.allows(Permissions::MANAGE_REQUESTS));
+}
+
+#[test]
+fn show_seasons_carry_their_own_status() {
+    let requests = requests();
+    let show = &requests.results[1];
+    assert_eq!(show.r#type, MediaType::Tv);
+    assert_eq!(show.media.status, MediaStatus::PartiallyAvailable);
+    assert_eq!(show.media.imdb_id.as_deref(), Some("tt0903747"));
+
+    let requested: Vec<_> = show
+        .seasons
+        .iter()
+        .map(|season| (season.season_number, season.status))
+        .collect();
+    assert_eq!(
+        requested,
+        [(1, RequestStatus::Approved), (2, RequestStatus::Approved)]
+    );
+
+    let media: Vec<_> = show
+        .media
+        .seasons
+        .iter()
+        .map(|season| (season.season_number, season.status))
+        .collect();
+    assert_eq!(
+        media,
+        [(1, MediaStatus::Available), (2, MediaStatus::Processing)]
+    );
+}
+
+#[test]
+fn unknown_codes_fall_back() {
+    let requests = requests();
+    let pending = &requests.results[2];
+    assert_eq!(pending.status, RequestStatus::PendingApproval);
+    assert_eq!(pending.media.status, MediaStatus::Other(9));
+    assert_eq!(pending.media.status4k, Some(MediaStatus::Pending));
+}
+
+#[test]
+fn unknown_fields_are_kept() {
+    let requests = requests();
+    let movie = &requests.results[0];
+    assert!(movie.extra.contains_key("isAutoRequest"));
+    assert_eq!(
+        movie.media.extra.get("serviceUrl").and_then(|v| v.as_str()),
+        Some("http://radarr:7878/movie/693134")
+    );
+    assert!(!movie.media.extra.contains_key("tmdbId"));
+}
+
+#[test]
+fn round_trips_without_losing_fields() {
+    let original: serde_json::Value = serde_json::from_str(REQUESTS).unwrap();
+    let reserialized = serde_json::to_value(requests()).unwrap();
+
+    let movie = &reserialized["results"][0];
+    assert_eq!(movie["status"], 2);
+    assert_eq!(movie["type"], "movie");
+    assert_eq!(movie["requestedBy"]["permissions"], 1082130464u64);
+    assert_eq!(movie["requestedBy"]["userType"], 3);
+    assert_eq!(
+        movie["media"]["serviceUrl"],
+        original["results"][0]["media"]["serviceUrl"]
+    );
+    assert_eq!(reserialized["results"][2]["media"]["status"], 9);
+}
+
+#[test]
+fn nested_models_keep_unknown_fields() {
+    let page: PageInfo = serde_json::from_str(
+        r#"{"page": 1, "pages": 1, "results": 0, "pageSize": 10, "cursor": "abc"}"#,
+    )
+    .unwrap();
+    assert_eq!(page.extra["cursor"], "abc");
+
+    let season: Season = serde_json::from_str(
+        r#"{"id": 1, "seasonNumber": 2, "status": 5, "status4k": 1, "airDate": "2024-01-01"}"#,
+    )
+    .unwrap();
+    assert_eq!(season.extra["airDate"], "2024-01-01");
+    assert_eq!(
+        serde_json::to_value(&season).unwrap()["airDate"],
+        "2024-01-01"
+    );
+
+    let requested: SeasonRequest =
+        serde_json::from_str(r#"{"id": 1, "seasonNumber": 2, "status": 1, "episodes": [3]}"#)
+            .unwrap();
+    assert_eq!(requested.extra["episodes"], serde_json::json!([3]));
+}
+
+/// Request pages recorded from real servers, see `fixtures/seerr/captured`.
+fn captured() -> Vec<(String, String)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/seerr/captured");
+    let mut pages: Vec<(String, String)> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let text = std::fs::read_to_string(&path).unwrap();
+            (path.display().to_string(), text)
+        })
+        .collect();
+    pages.sort();
+    pages
+}
+
+#[test]
+fn captured_pages_deserialize_and_round_trip() {
+    let pages = captured();
+    if pages.is_empty() {
+        eprintln!("no captured Seerr payloads, see fixtures/seerr/captured/README.md");
+        return;
+    }
+
+    let mut user_types = Vec::new();
+    for (path, text) in &pages {
+        let requests: Requests =
+            serde_json::from_str(text).unwrap_or_else(|e| panic!("{path} should deserialize: {e}"));
+        user_types.extend(
+            requests
+                .results
+                .iter()
+                .map(|request| request.requested_by.user_type),
+        );
+
+        let original: serde_json::Value = serde_json::from_str(text).unwrap();
+        let reserialized = serde_json::to_value(&requests).unwrap();
+        assert_eq!(
+            reserialized["results"].as_array().map(Vec::len),
+            original["results"].as_array().map(Vec::len),
+            "{path} lost requests"
+        );
+        for (request, recorded) in reserialized["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(original["results"].as_array().unwrap())
+        {
+            for key in recorded.as_object().unwrap().keys() {
+                assert!(
+                    request.get(key).is_some(),
+                    "{path} lost field {key} of request {}",
+                    recorded["id"]
+                );
+            }
+        }
+    }
+    assert!(
+        user_types.contains(&UserType::Plex),
+        "captured pages need a Plex user"
+    );
+    assert!(
+        user_types.contains(&UserType::Local),
+        "captured pages need a local user"
+    );
+}
@@ -0,0 +1,126 @@
+use clients::trakt::structs::{Audio, CollectionMediaType, CollectionMetadata, Hdr, Resolution};
+use serde_json::json;
+
+fn names<T: serde::Serialize>(values: &[T]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| {
+            serde_json::to_value(value)
+                .unwrap()
+                .as_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn media_types_use_trakt_names() {
+    use CollectionMediaType::*;
+    assert_eq!(
+        names(&[Digital, Bluray, Hddvd, Dvd, Vcd, Vhs, Betamax, Laserdisc]),
+        [
+            "digital",
+            "bluray",
+            "hddvd",
+            "dvd",
+            "vcd",
+            "vhs",
+            "betamax",
+            "laserdisc"
+        ]
+    );
+}
+
+#[test]
+fn resolutions_use_trakt_names() {
+    use Resolution::*;
+    assert_eq!(
+        names(&[Uhd4k, Hd1080p, Hd1080i, Hd720p, Sd480p, Sd480i, Sd576p, Sd576i]),
+        ["uhd_4k", "hd_1080p", "hd_1080i", "hd_720p", "sd_480p", "sd_480i", "sd_576p", "sd_576i"]
+    );
+}
+
+#[test]
+fn hdr_formats_use_trakt_names() {
+    use Hdr::*;
+    assert_eq!(
+        names(&[DolbyVision, Hdr10, Hdr10Plus, Hlg]),
+        ["dolby_vision", "hdr10", "hdr10_plus", "hlg"]
+    );
+}
+
+#[test]
+fn audio_formats_use_trakt_names() {
+    use Audio::*;
+    assert_eq!(
+        names(&[
+            DolbyDigital,
+            DolbyDigitalPlus,
+            DolbyDigitalPlusAtmos,
+            DolbyTruehd,
+            DolbyAtmos,
+            DolbyPrologic,
+            Dts,
+            DtsMa,
+            DtsHr,
+            DtsX,
+            Auro3d,
+            Mp3,
+            Mp2,
+            Aac,
+            Lpcm,
+            Ogg,
+            OggOpus,
+            Wma,
+            Flac,
+        ]),
+        [
+            "dolby_digital",
+            "dolby_digital_plus",
+            "dolby_digital_plus_atmos",
+            "dolby_truehd",
+            "dolby_atmos",
+            "dolby_prologic",
+            "dts",
+            "dts_ma",
+            "dts_hr",
+            "dts_x",
+            "auro_3d",
+            "mp3",
+            "mp2",
+            "aac",
+            "lpcm",
+            "ogg",
+            "ogg_opus",
+            "wma",
+            "flac",
+        ]
+    );
+}
+
+#[test]
+fn collection_metadata_leaves_out_unknown_properties() {
+    let metadata = CollectionMetadata {
+        media_type: Some(CollectionMediaType::Digital),
+        resolution: Some(Resolution::Uhd4k),
+        hdr: Some(Hdr::DolbyVision),
+        audio: Some(Audio::Auro3d),
+        audio_channels: Some("7.1".to_string()),
+        three_d: None,
+    };
+    assert_eq!(
+        serde_json::to_value(&metadata).unwrap(),
+        json!({
+            "media_type": "digital",
+            "resolution": "uhd_4k",
+            "hdr": "dolby_vision",
+            "audio": "auro_3d",
+            "audio_channels": "7.1",
+        })
+    );
+    assert_eq!(
+        serde_json::to_value(CollectionMetadata::default()).unwrap(),
+        json!({})
+    );
+}
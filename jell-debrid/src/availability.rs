@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+
+use clients::jellyfin::client::JellyfinClient;
+use clients::jellyfin::structs::ItemQuery;
+use clients::media::MediaType;
+use clients::seerrs::client::SeerrClient;
+use clients::seerrs::structs::{MediaRequest, MediaStatus, MediaStatusUpdate};
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::{IdLookup, SearchResultItem};
+use clients::Error;
+
+/// Marks Seerr media available, or partially available, once it shows up in
+/// Jellyfin. 4K requests are left to Seerr's own scan: Jellyfin is searched
+/// by provider id, which cannot tell a 4K copy from any other.
+#[derive(Default)]
+pub struct AvailabilityReporter {
+    /// Last status set per Seerr media id.
+    reported: HashMap<u64, MediaStatusUpdate>,
+}
+
+impl AvailabilityReporter {
+    /// Checks the unfulfilled Seerr requests, using `fetched` if this pass
+    /// already loaded them.
+    pub async fn report(
+        &mut self,
+        seerr: &SeerrClient,
+        jellyfin: &JellyfinClient,
+        trakt: &TraktClient,
+        fetched: Option<Vec<MediaRequest>>,
+    ) {
+        let requests = match fetched {
+            Some(requests) => requests,
+            None => match seerr.get_unfulfilled_requests().await {
+                Ok(requests) => requests,
+                Err(e) => {
+                    crate::report_error("Seerr", &e);
+                    return;
+                }
+            },
+        };
+        for request in requests {
+            if request.is4k || request.media.status == MediaStatus::Available {
+                continue;
+            }
+            let status = match availability(jellyfin, trakt, &request).await {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(e) => {
+                    crate::report_error("Jellyfin", &e);
+                    continue;
+                }
+            };
+            if self.reported.get(&request.media.id) == Some(&status) {
+                continue;
+            }
+            log::info!(
+                "Marking Seerr media {} (TMDB {}) {}",
+                request.media.id,
+                request.media.tmdb_id,
+                status.as_str()
+            );
+            match seerr
+                .set_media_status(request.media.id, status, false)
+                .await
+            {
+                Ok(_) => {
+                    self.reported.insert(request.media.id, status);
+                }
+                Err(e) => crate::report_error("Seerr", &e),
+            }
+        }
+    }
+}
+
+/// How much of a request is in Jellyfin. A show is available once every
+/// aired episode of its regular seasons is there, and partially available
+/// once any episode is. Seerr can only mark a whole show
+/// available, so per-season progress ends up as partial.
+async fn availability(
+    jellyfin: &JellyfinClient,
+    trakt: &TraktClient,
+    request: &MediaRequest,
+) -> Result<Option<MediaStatusUpdate>, Error> {
+    let (media_type, item_type) = match request.r#type.as_media_type() {
+        Some(MediaType::Movie) => (MediaType::Movie, "Movie"),
+        Some(MediaType::Show) => (MediaType::Show, "Series"),
+        None => return Ok(None),
+    };
+    let found = jellyfin
+        .get_items(&ItemQuery {
+            recursive: Some(true),
+            include_item_types: Some(item_type.to_string()),
+            any_provider_id_equals: Some(format!("tmdb.{}", request.media.tmdb_id)),
+            ..Default::default()
+        })
+        .await?;
+    let Some(item) = found.first() else {
+        return Ok(None);
+    };
+    if media_type == MediaType::Movie {
+        return Ok(Some(MediaStatusUpdate::Available));
+    }
+
+    let present: HashSet<(u32, u32)> = jellyfin
+        .get_items(&ItemQuery {
+            recursive: Some(true),
+            include_item_types: Some("Episode".to_string()),
+            parent_id: Some(item.id.clone()),
+            ..Default::default()
+        })
+        .await?
+        .iter()
+        .filter_map(|episode| Some((episode.parent_index_number?, episode.index_number?)))
+        .collect();
+    if present.is_empty() {
+        return Ok(None);
+    }
+
+    // Trakt failures only cost the upgrade from partial to available.
+    let aired = match aired_episodes(trakt, request.media.tmdb_id).await {
+        Ok(aired) => aired,
+        Err(e) => {
+            crate::report_error("Trakt", &e);
+            return Ok(Some(MediaStatusUpdate::PartiallyAvailable));
+        }
+    };
+    let complete = !aired.is_empty() && aired.iter().all(|episode| present.contains(episode));
+    Ok(Some(if complete {
+        MediaStatusUpdate::Available
+    } else {
+        MediaStatusUpdate::PartiallyAvailable
+    }))
+}
+
+/// The `(season, episode)` numbers of a show's aired regular episodes.
+async fn aired_episodes(trakt: &TraktClient, tmdb: u64) -> Result<Vec<(u32, u32)>, Error> {
+    let search = trakt
+        .search(&IdLookup::Tmdb(tmdb), Some(MediaType::Show))
+        .await?;
+    let trakt_id = search.searchresult.iter().find_map(|item| match item {
+        SearchResultItem::Show(show) => show.show.ids.trakt,
+        _ => None,
+    });
+    let Some(trakt_id) = trakt_id else {
+        return Ok(Vec::new());
+    };
+    let now = chrono::Utc::now();
+    Ok(trakt
+        .get_show_seasons(&trakt_id.to_string())
+        .await?
+        .iter()
+        .filter(|season| season.number > 0)
+        .flat_map(|season| &season.episodes)
+        .filter(|episode| episode.has_aired(now))
+        .map(|episode| (episode.season, episode.number))
+        .collect())
+}
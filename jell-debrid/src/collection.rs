@@ -0,0 +1,323 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use clients::jellyfin::client::JellyfinClient;
+use clients::jellyfin::structs::{BaseItem, ItemQuery, MediaStream};
+use clients::media::MediaType;
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::{
+    Audio, CollectionMediaType, CollectionMetadata, Hdr, IDs, Resolution, SyncEpisode, SyncItems,
+    SyncMovie, SyncSeason, SyncShow,
+};
+use clients::Error;
+
+use crate::sources::AcquisitionRequest;
+
+/// How long to wait before looking for a request in Jellyfin again.
+const RECHECK_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Item fields needed to describe a copy.
+const METADATA_FIELDS: &str = "MediaStreams";
+
+/// Mirrors what has landed in Jellyfin into the Trakt collection.
+pub struct CollectionSync {
+    /// Also take collected movies and episodes off the Trakt watchlist.
+    /// Whole shows stay on it so new episodes keep being followed.
+    remove_from_watchlist: bool,
+    /// Jellyfin ids of the items already added to the collection.
+    synced: HashSet<String>,
+    /// IMDb ids of the movies already collected, which need no more checks.
+    collected_movies: HashSet<String>,
+    /// When each request was last looked for in Jellyfin.
+    checked: HashMap<String, Instant>,
+}
+
+impl CollectionSync {
+    pub fn new(remove_from_watchlist: bool) -> Self {
+        Self {
+            remove_from_watchlist,
+            synced: HashSet::new(),
+            collected_movies: HashSet::new(),
+            checked: HashMap::new(),
+        }
+    }
+
+    /// Adds whatever Jellyfin holds of `request` and was not synced yet to
+    /// the Trakt collection. Jellyfin is asked at most once every
+    /// `RECHECK_AFTER` per request, and no more once a movie is collected.
+    pub async fn sync(
+        &mut self,
+        jellyfin: &JellyfinClient,
+        trakt: &TraktClient,
+        request: &AcquisitionRequest,
+    ) -> Result<(), Error> {
+        let Some(imdb) = request.media.ids.imdb.as_deref() else {
+            return Ok(());
+        };
+        if self.collected_movies.contains(imdb) {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let key = format!(
+            "{imdb}:{:?}:{:?}",
+            request.media.season, request.media.episode
+        );
+        if self
+            .checked
+            .get(&key)
+            .is_some_and(|last| now.duration_since(*last) < RECHECK_AFTER)
+        {
+            return Ok(());
+        }
+        self.checked.insert(key, now);
+
+        let ids = IDs::from(&request.media.ids);
+        let item_type = match request.media.media_type {
+            MediaType::Movie => "Movie",
+            MediaType::Show => "Series",
+        };
+        let found = jellyfin
+            .get_items(&ItemQuery {
+                recursive: Some(true),
+                include_item_types: Some(item_type.to_string()),
+                fields: Some(METADATA_FIELDS.to_string()),
+                any_provider_id_equals: Some(format!("imdb.{imdb}")),
+                ..Default::default()
+            })
+            .await?;
+
+        let mut new_items = Vec::new();
+        let mut items = SyncItems::default();
+        match request.media.media_type {
+            MediaType::Movie => {
+                let Some(movie) = found.iter().find(|item| !self.synced.contains(&item.id)) else {
+                    return Ok(());
+                };
+                items.movies.push(SyncMovie {
+                    ids: ids.clone(),
+                    collected_at: None,
+                    metadata: metadata(movie),
+                });
+                new_items.push(movie.id.clone());
+            }
+            MediaType::Show => {
+                let Some(series) = found.first() else {
+                    return Ok(());
+                };
+                let episodes = jellyfin
+                    .get_items(&ItemQuery {
+                        recursive: Some(true),
+                        include_item_types: Some("Episode".to_string()),
+                        fields: Some(METADATA_FIELDS.to_string()),
+                        parent_id: Some(series.id.clone()),
+                        ..Default::default()
+                    })
+                    .await?;
+                let mut seasons: Vec<SyncSeason> = Vec::new();
+                for episode in episodes {
+                    let (Some(season), Some(number)) =
+                        (episode.parent_index_number, episode.index_number)
+                    else {
+                        continue;
+                    };
+                    if self.synced.contains(&episode.id) || !wanted(request, season, number) {
+                        continue;
+                    }
+                    let sync_episode = SyncEpisode {
+                        number: Some(number),
+                        metadata: metadata(&episode),
+                        ..Default::default()
+                    };
+                    match seasons.iter_mut().find(|s| s.number == season) {
+                        Some(s) => s.episodes.push(sync_episode),
+                        None => seasons.push(SyncSeason {
+                            number: season,
+                            episodes: vec![sync_episode],
+                        }),
+                    }
+                    new_items.push(episode.id);
+                }
+                if seasons.is_empty() {
+                    return Ok(());
+                }
+                items.shows.push(SyncShow {
+                    ids: ids.clone(),
+                    seasons,
+                });
+            }
+        }
+
+        let response = trakt.add_to_collection(&items).await?;
+        if let Some(added) = &response.added {
+            log::info!(
+                "Added {} movies and {} episodes to the Trakt collection",
+                added.movies,
+                added.episodes
+            );
+        }
+        if !response.not_found.is_empty() {
+            log::warn!("Trakt did not find {:?}", response.not_found);
+        }
+        self.synced.extend(new_items);
+        if request.media.media_type == MediaType::Movie {
+            self.collected_movies.insert(imdb.to_string());
+        }
+
+        if self.remove_from_watchlist && request.sources.contains(&"trakt-watchlist") {
+            self.unwatchlist(trakt, request, ids).await?;
+        }
+        Ok(())
+    }
+
+    async fn unwatchlist(
+        &self,
+        trakt: &TraktClient,
+        request: &AcquisitionRequest,
+        ids: IDs,
+    ) -> Result<(), Error> {
+        let mut items = SyncItems::default();
+        match (
+            request.media.media_type,
+            request.media.season,
+            request.media.episode,
+        ) {
+            (MediaType::Movie, _, _) => items.movies.push(SyncMovie {
+                ids,
+                collected_at: None,
+                metadata: CollectionMetadata::default(),
+            }),
+            (MediaType::Show, Some(season), Some(number)) => items.shows.push(SyncShow {
+                ids,
+                seasons: vec![SyncSeason {
+                    number: season,
+                    episodes: vec![SyncEpisode {
+                        number: Some(number),
+                        ..Default::default()
+                    }],
+                }],
+            }),
+            _ => return Ok(()),
+        }
+        let response = trakt.remove_from_watchlist(&items).await?;
+        if let Some(deleted) = response.deleted {
+            log::info!(
+                "Removed {} movies and {} episodes from the Trakt watchlist",
+                deleted.movies,
+                deleted.episodes
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Whether episode `number` of `season` is part of a show request.
+fn wanted(request: &AcquisitionRequest, season: u32, number: u32) -> bool {
+    match (request.media.season, request.media.episode) {
+        (Some(s), Some(e)) => s == season && e == number,
+        (Some(s), None) => s == season,
+        _ => request.seasons.is_empty() || request.seasons.contains(&season),
+    }
+}
+
+/// What can be told about a copy from its Jellyfin item and its default
+/// video and audio streams.
+fn metadata(item: &BaseItem) -> CollectionMetadata {
+    let video = default_stream(item, "Video");
+    let audio = default_stream(item, "Audio");
+    CollectionMetadata {
+        media_type: Some(match item.video_type.as_deref() {
+            Some("BluRay") => CollectionMediaType::Bluray,
+            Some("Dvd") => CollectionMediaType::Dvd,
+            Some("HdDvd") => CollectionMediaType::Hddvd,
+            _ => CollectionMediaType::Digital,
+        }),
+        resolution: match video {
+            Some(video) if video.width.is_some() => resolution(video.width, video.height),
+            _ => resolution(item.width, item.height),
+        },
+        hdr: video.and_then(hdr),
+        audio: audio.and_then(audio_codec),
+        audio_channels: audio.and_then(audio_channels),
+        ..Default::default()
+    }
+}
+
+/// The stream of `kind` players pick by default, or else the first one.
+fn default_stream<'a>(item: &'a BaseItem, kind: &str) -> Option<&'a MediaStream> {
+    let mut streams = item.media_streams.iter().filter(|s| s.r#type == kind);
+    let first = streams.clone().next();
+    streams.find(|s| s.is_default).or(first)
+}
+
+fn hdr(video: &MediaStream) -> Option<Hdr> {
+    match video.video_range_type.as_deref()? {
+        range if range.starts_with("DOVI") => Some(Hdr::DolbyVision),
+        "HDR10" => Some(Hdr::Hdr10),
+        "HDR10Plus" => Some(Hdr::Hdr10Plus),
+        "HLG" => Some(Hdr::Hlg),
+        _ => None,
+    }
+}
+
+/// Maps an ffmpeg codec name, refined by its profile, to a Trakt audio format.
+fn audio_codec(audio: &MediaStream) -> Option<Audio> {
+    let profile = audio.profile.as_deref().unwrap_or("");
+    let atmos = profile.contains("Atmos");
+    Some(
+        match audio.codec.as_deref()?.to_ascii_lowercase().as_str() {
+            "ac3" => Audio::DolbyDigital,
+            "eac3" if atmos => Audio::DolbyDigitalPlusAtmos,
+            "eac3" => Audio::DolbyDigitalPlus,
+            "truehd" if atmos => Audio::DolbyAtmos,
+            "truehd" => Audio::DolbyTruehd,
+            "dts" if profile.contains("DTS:X") => Audio::DtsX,
+            "dts" if profile.contains("MA") => Audio::DtsMa,
+            "dts" if profile.contains("HRA") => Audio::DtsHr,
+            "dts" => Audio::Dts,
+            "aac" => Audio::Aac,
+            "mp3" => Audio::Mp3,
+            "mp2" => Audio::Mp2,
+            "flac" => Audio::Flac,
+            "opus" => Audio::OggOpus,
+            "vorbis" => Audio::Ogg,
+            "wmav1" | "wmav2" | "wmapro" => Audio::Wma,
+            codec if codec.starts_with("pcm") => Audio::Lpcm,
+            _ => return None,
+        },
+    )
+}
+
+/// The channel layout as Trakt writes it, such as `5.1`.
+fn audio_channels(audio: &MediaStream) -> Option<String> {
+    let layout = audio.channel_layout.as_deref().unwrap_or("");
+    match layout {
+        "mono" => return Some("1.0".to_string()),
+        "stereo" => return Some("2.0".to_string()),
+        _ => {}
+    }
+    // Drop qualifiers such as the `(side)` of `5.1(side)`.
+    let layout = layout.split('(').next().unwrap_or("");
+    if !layout.is_empty() && layout.split('.').all(|n| n.parse::<u32>().is_ok()) {
+        return Some(layout.to_string());
+    }
+    match audio.channels? {
+        0 => None,
+        channels @ 1..=5 => Some(format!("{channels}.0")),
+        channels => Some(format!("{}.1", channels - 1)),
+    }
+}
+
+/// Maps a frame size to the closest Trakt resolution, allowing for cropped
+/// and letterboxed encodes.
+fn resolution(width: Option<u32>, height: Option<u32>) -> Option<Resolution> {
+    let (width, height) = (width?, height?);
+    Some(if width >= 3200 || height >= 2000 {
+        Resolution::Uhd4k
+    } else if width >= 1800 || height >= 1000 {
+        Resolution::Hd1080p
+    } else if width >= 1200 || height >= 700 {
+        Resolution::Hd720p
+    } else {
+        Resolution::Sd480p
+    })
+}
@@ -1,13 +1,34 @@
+mod availability;
+mod collection;
+mod moderation;
+mod scheduler;
+mod scrobble;
+mod sources;
+
+use clients::cache::{CacheKind, MetadataCache};
 use clients::jellyfin::client::JellyfinClient;
+use clients::media::{MediaIdentity, MediaType};
+use clients::realdebrid::client::RealDebridClient;
 use clients::seerrs::client::SeerrClient;
+use clients::trakt::auth::{DeviceAuthError, TokenStore, TraktOAuthToken};
 use clients::trakt::client::TraktClient;
 
-use clients::trakt::structs::SearchResultItem;
-use env_logger;
+use availability::AvailabilityReporter;
+use clients::trakt::structs::{IdLookup, SearchResult, SearchResultItem};
+use clients::Error;
+use collection::CollectionSync;
 use figment::{providers::Env, Figment};
+use moderation::SeerrModerator;
+use scheduler::EpisodeScheduler;
+use scrobble::{ScrobbleUser, Scrobbler};
 use serde::Deserialize;
 use tokio::time;
 
+use crate::sources::{
+    AcquisitionRequest, FetchedSeerrRequests, ListSubscription, RequestSource, SeerrSource,
+    TraktListSource, TraktWatchlistSource,
+};
+
 #[derive(Debug, Default, Deserialize, PartialEq, Eq)]
 struct AppConfig {
     jf_api_key: String,
@@ -17,15 +38,97 @@ struct AppConfig {
     trakt_api_key: Option<String>,
     trakt_client_id: String,
     trakt_client_secret: Option<String>,
+    trakt_token_path: Option<String>,
+
+    /// Comma-separated request sources to poll, all of them by default.
+    request_sources: Option<String>,
+    /// Comma-separated Trakt lists to follow, as `user/list` or `liked`.
+    trakt_lists: Option<String>,
+    /// Minutes to wait after an episode airs before acquiring it, 60 by
+    /// default.
+    episode_delay_minutes: Option<i64>,
+    /// Add what lands in Jellyfin to the Trakt collection, on by default.
+    trakt_collection_sync: Option<bool>,
+    /// Take collected movies and episodes off the Trakt watchlist.
+    trakt_unwatchlist_collected: Option<bool>,
+    /// Comma-separated Jellyfin users to scrobble to Trakt, as
+    /// `jellyfin_user=token_file`.
+    scrobble_users: Option<String>,
+    /// SQLite file id mappings and metadata are cached in across restarts.
+    cache_path: Option<String>,
+    /// Comma-separated Seerr user ids, or Jellyfin or Plex usernames, whose
+    /// requests are approved right away.
+    seerr_trusted_users: Option<String>,
+    /// Decline Seerr requests for titles Trakt does not know.
+    seerr_decline_unresolvable: Option<bool>,
+    /// Mark Seerr media available once it is in Jellyfin, on by default.
+    seerr_report_availability: Option<bool>,
 }
 
+/// The Trakt application tokens are issued to.
+#[derive(Debug, Clone)]
+pub(crate) struct TraktCredentials {
+    client_id: String,
+    client_secret: String,
+}
+
+/// Refresh the Trakt access token once it is this close to expiring.
+const TRAKT_REFRESH_MARGIN: time::Duration = time::Duration::from_secs(60 * 60);
+
+const DEFAULT_SOURCES: &str = "seerr,trakt-watchlist,trakt-lists,trakt-calendar";
+
+/// How often the Trakt calendar is fetched again.
+const CALENDAR_REFRESH_HOURS: i64 = 6;
+
+/// How often Jellyfin sessions are checked for playback to scrobble.
+const SCROBBLE_INTERVAL: time::Duration = time::Duration::from_secs(15);
+
+const DEFAULT_CACHE_PATH: &str = "jell-debrid-cache.sqlite";
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
 
-    let cfg: AppConfig = Figment::new().merge(Env::raw()).extract().unwrap();
+    let figment = Figment::new().merge(Env::raw());
+    let cache_path = figment
+        .extract_inner::<String>("cache_path")
+        .unwrap_or_else(|_| DEFAULT_CACHE_PATH.to_string());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("cache") {
+        run_cache_command(&cache_path, &args[1..]);
+        return;
+    }
+
+    let cfg: AppConfig = figment.extract().unwrap();
 
-    let jellyfin = JellyfinClient::new("http://192.168.0.69:8096", &cfg.jf_api_key);
+    // OAuth is needed for the main token unless a key is configured, and for
+    // every scrobbling user.
+    let uses_oauth = cfg.trakt_api_key.is_none()
+        || cfg
+            .scrobble_users
+            .as_deref()
+            .is_some_and(|users| !users.trim().is_empty());
+    let client_secret = match cfg.trakt_client_secret.clone().filter(|s| !s.is_empty()) {
+        Some(secret) => secret,
+        None if uses_oauth => {
+            log::error!(
+                "TRAKT_CLIENT_SECRET must be set to authorize with Trakt or refresh its tokens"
+            );
+            return;
+        }
+        None => String::new(),
+    };
+    let credentials = TraktCredentials {
+        client_id: cfg.trakt_client_id.clone(),
+        client_secret,
+    };
+
+    let Some(jellyfin) = configured(
+        "Jellyfin",
+        JellyfinClient::new("http://192.168.0.69:8096", &cfg.jf_api_key),
+    ) else {
+        return;
+    };
     let response = jellyfin.get_system_info().await;
     match response {
         Ok(v) => println!(" in main deserialized = {:?}", v),
@@ -38,43 +141,442 @@ async fn main() {
         Err(e) => println!("in main error: {e:?}"),
     }
 
-    let token = if cfg.trakt_api_key.is_none() {
-        let token =
-            TraktClient::oauth2(&cfg.trakt_client_id, &cfg.trakt_client_secret.unwrap()).await;
-        println!("Got new trakt token = {:?}", token.access_token);
-        token.access_token
+    let Some(realdebrid) = configured("Real-Debrid", RealDebridClient::new(&cfg.rd_api_key)) else {
+        return;
+    };
+    match realdebrid.get_user().await {
+        Ok(user) => log::info!(
+            "Signed in to Real-Debrid as {} ({} account, expires {})",
+            user.username,
+            user.r#type,
+            user.expiration
+        ),
+        Err(e) => report_error("Real-Debrid", &e),
+    }
+
+    let scrobble_users = ScrobbleUser::load_all(
+        cfg.scrobble_users.as_deref().unwrap_or(""),
+        &cfg.trakt_client_id,
+    );
+    let scrobbler = (!scrobble_users.is_empty())
+        .then(|| Scrobbler::new(jellyfin.clone(), credentials.clone(), scrobble_users));
+
+    let token_store = TokenStore::new(
+        cfg.trakt_token_path
+            .as_deref()
+            .unwrap_or("trakt_token.json"),
+    );
+    let mut oauth_token = None;
+    let token = if let Some(token) = cfg.trakt_api_key.clone() {
+        token
     } else {
-        cfg.trakt_api_key.unwrap()
+        let token = match token_store.load() {
+            Ok(Some(token)) => Ok(token),
+            Ok(None) => authorize_trakt(&credentials, &token_store).await,
+            Err(e) => {
+                log::warn!("Ignoring stored Trakt token: {e}");
+                authorize_trakt(&credentials, &token_store).await
+            }
+        };
+        let mut token = match token {
+            Ok(token) => token,
+            Err(e) => {
+                log::error!("Trakt authorization failed: {e}");
+                return;
+            }
+        };
+        if let Some(refreshed) = refresh_trakt_token(&credentials, &token_store, &token).await {
+            token = refreshed;
+        }
+        let access_token = token.access_token.clone();
+        oauth_token = Some(token);
+        access_token
     };
 
-    let mut trakt = TraktClient::new(&token, &cfg.trakt_client_id);
-    let seerr = SeerrClient::new("http://192.168.0.69:5055", &cfg.seerr_api_key);
+    let Some(mut trakt) = configured("Trakt", TraktClient::new(&token, &cfg.trakt_client_id))
+    else {
+        return;
+    };
+    match MetadataCache::open(&cache_path) {
+        Ok(cache) => trakt = trakt.with_metadata_cache(cache),
+        Err(e) => log::warn!("Not caching metadata in {cache_path}: {e}"),
+    }
+    let Some(seerr) = configured(
+        "Seerr",
+        SeerrClient::new("http://192.168.0.69:5055", &cfg.seerr_api_key),
+    ) else {
+        return;
+    };
+
+    let mut calendar = source_names(&cfg)
+        .any(|name| name == "trakt-calendar")
+        .then(|| {
+            EpisodeScheduler::new(
+                chrono::Duration::minutes(cfg.episode_delay_minutes.unwrap_or(60)),
+                chrono::Duration::hours(CALENDAR_REFRESH_HOURS),
+            )
+        });
+
+    let mut collection = cfg
+        .trakt_collection_sync
+        .unwrap_or(true)
+        .then(|| CollectionSync::new(cfg.trakt_unwatchlist_collected.unwrap_or(false)));
+
+    let moderator = SeerrModerator::new(
+        cfg.seerr_trusted_users.as_deref().unwrap_or(""),
+        cfg.seerr_decline_unresolvable.unwrap_or(false),
+    );
+
+    let mut availability = cfg
+        .seerr_report_availability
+        .unwrap_or(true)
+        .then(AvailabilityReporter::default);
+
+    let seerr_requests = FetchedSeerrRequests::default();
+    let sources = enabled_sources(&cfg, &seerr, &trakt, &seerr_requests);
+    let pipeline = async {
+        let mut interval = time::interval(time::Duration::from_secs(100));
+        loop {
+            interval.tick().await;
+
+            if let Some(token) = &oauth_token {
+                if let Some(refreshed) =
+                    refresh_trakt_token(&credentials, &token_store, token).await
+                {
+                    match trakt.set_access_token(&refreshed.access_token) {
+                        Ok(()) => oauth_token = Some(refreshed),
+                        Err(e) => report_error("Trakt", &e),
+                    }
+                }
+            }
+
+            if moderator.is_enabled() {
+                moderator.moderate(&seerr, &trakt).await;
+            }
+
+            let mut requests = sources::collect_requests(&sources).await;
 
-    let mut interval = time::interval(time::Duration::from_secs(100));
+            if let Some(calendar) = calendar.as_mut() {
+                let now = chrono::Utc::now();
+                if let Err(e) = calendar.refresh(&trakt, now).await {
+                    report_error("Trakt calendar", &e);
+                }
+                for request in calendar.due(now) {
+                    sources::push_merged(&mut requests, request);
+                }
+            }
+
+            for mut request in requests {
+                log::debug!(
+                    "Handling request from {:?}: {:?} {:?}",
+                    request.sources,
+                    request.media,
+                    request.seasons
+                );
+                if request.media.ids.imdb.is_none() {
+                    request.media.ids.imdb = resolve_imdb(&trakt, &request.media).await;
+                }
+                let Some(imdb) = request.media.ids.imdb.clone() else {
+                    continue;
+                };
+                log::debug!("Resolved request to {imdb}");
+
+                if request.media.media_type == MediaType::Show && request.media.episode.is_none() {
+                    match aired_seasons(&trakt, &imdb, &request).await {
+                        Some(seasons) if seasons.is_empty() => {
+                            log::info!("Nothing requested of {imdb} has aired yet");
+                            continue;
+                        }
+                        Some(seasons) => request.seasons = seasons,
+                        None => {}
+                    }
+                }
+
+                if let Some(collection) = collection.as_mut() {
+                    if let Err(e) = collection.sync(&jellyfin, &trakt, &request).await {
+                        report_error("Trakt collection", &e);
+                    }
+                }
+            }
+
+            let fetched = seerr_requests.take();
+            if let Some(availability) = availability.as_mut() {
+                availability
+                    .report(&seerr, &jellyfin, &trakt, fetched)
+                    .await;
+            }
+        }
+    };
+    tokio::join!(pipeline, run_scrobbler(scrobbler));
+}
+
+async fn run_scrobbler(scrobbler: Option<Scrobbler>) {
+    let Some(mut scrobbler) = scrobbler else {
+        return;
+    };
+    let mut interval = time::interval(SCROBBLE_INTERVAL);
     loop {
         interval.tick().await;
+        scrobbler.poll().await;
+    }
+}
 
-        let requests = seerr.get_unfulfilled_requests().await.unwrap();
-        for val in requests.results.iter() {
-            println!(
-                "------------\nHandling overseer request: {:?} {:?} {:?} {:?}",
-                val.r#type, val.created_at, val.media.tmdb_id, val.seasons
-            );
-            let search = trakt
-                .search(val.media.tmdb_id, &val.r#type)
-                .await
-                .as_ref()
-                .unwrap();
-            let search = search.searchresult.first().unwrap();
-
-            let imdb = match search {
-                SearchResultItem::Movie(movie) => &movie.movie.ids.imdb,
-                SearchResultItem::Show(show) => &show.show.ids.imdb,
-                SearchResultItem::Episode(episode) => &episode.episode.ids.imdb,
-                SearchResultItem::Person(person) => &person.person.ids.imdb,
+/// The seasons of a show request with at least one aired episode, leaving
+/// out specials unless they were asked for explicitly. `None` if Trakt could
+/// not tell, in which case the request is kept as it is.
+async fn aired_seasons(
+    trakt: &TraktClient,
+    imdb: &str,
+    request: &AcquisitionRequest,
+) -> Option<Vec<u32>> {
+    let seasons = match trakt.get_show_seasons(imdb).await {
+        Ok(seasons) => seasons,
+        Err(e) => {
+            report_error("Trakt", &e);
+            return None;
+        }
+    };
+    let wanted = |season: u32| match request.media.season {
+        Some(only) => season == only,
+        None if request.seasons.is_empty() => season > 0,
+        None => request.seasons.contains(&season),
+    };
+    let now = chrono::Utc::now();
+    let aired: Vec<(u32, u32)> = seasons
+        .iter()
+        .filter(|season| wanted(season.number))
+        .flat_map(|season| &season.episodes)
+        .filter(|episode| episode.has_aired(now))
+        .map(|episode| (episode.season, episode.number))
+        .collect();
+    log::debug!("Aired episodes of {imdb}: {aired:?}");
+
+    let mut numbers: Vec<u32> = aired.iter().map(|(season, _)| *season).collect();
+    numbers.dedup();
+    Some(numbers)
+}
+
+fn enabled_sources<'a>(
+    cfg: &AppConfig,
+    seerr: &'a SeerrClient,
+    trakt: &'a TraktClient,
+    seerr_requests: &FetchedSeerrRequests,
+) -> Vec<Box<dyn RequestSource + 'a>> {
+    let mut sources: Vec<Box<dyn RequestSource + 'a>> = Vec::new();
+    for name in source_names(cfg) {
+        match name {
+            "seerr" => sources.push(Box::new(SeerrSource::new(seerr, seerr_requests.clone()))),
+            "trakt-watchlist" => sources.push(Box::new(TraktWatchlistSource::new(trakt))),
+            "trakt-lists" => {
+                let lists = ListSubscription::parse_all(cfg.trakt_lists.as_deref().unwrap_or(""));
+                if !lists.is_empty() {
+                    sources.push(Box::new(TraktListSource::new(trakt, lists)));
+                }
+            }
+            // Followed by the episode scheduler in the main loop.
+            "trakt-calendar" => {}
+            other => log::warn!("Ignoring unknown request source {other}"),
+        }
+    }
+    sources
+}
+
+fn source_names(cfg: &AppConfig) -> impl Iterator<Item = &str> {
+    cfg.request_sources
+        .as_deref()
+        .unwrap_or(DEFAULT_SOURCES)
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Looks up the IMDb id of a title through its other ids, falling back to
+/// its title and year.
+async fn resolve_imdb(trakt: &TraktClient, media: &MediaIdentity) -> Option<String> {
+    let ids = &media.ids;
+    let lookups = [
+        ids.tmdb.map(IdLookup::Tmdb),
+        ids.tvdb.map(IdLookup::Tvdb),
+        ids.trakt.map(IdLookup::Trakt),
+    ];
+    for lookup in lookups.into_iter().flatten() {
+        match trakt.search(&lookup, Some(media.media_type)).await {
+            Ok(search) => {
+                if let Some(imdb) = first_imdb(&search, |_| true) {
+                    return Some(imdb);
+                }
+                log::warn!("No IMDb id on Trakt for {lookup:?}");
+            }
+            Err(e) => report_error("Trakt", &e),
+        }
+    }
+
+    let title = media.title.as_deref()?;
+    let search = match trakt
+        .search_text(title, &[media.media_type], media.year)
+        .await
+    {
+        Ok(search) => search,
+        Err(e) => {
+            report_error("Trakt", &e);
+            return None;
+        }
+    };
+    // Text search ranks loosely related titles too, only take exact ones.
+    let imdb = first_imdb(&search, |found| {
+        found
+            .title
+            .as_deref()
+            .is_some_and(|found| found.eq_ignore_ascii_case(title))
+    });
+    if imdb.is_none() {
+        log::warn!(
+            "No Trakt match with an IMDb id for {title} ({:?})",
+            media.year
+        );
+    }
+    imdb
+}
+
+fn first_imdb(search: &SearchResult, accept: impl Fn(&MediaIdentity) -> bool) -> Option<String> {
+    search
+        .searchresult
+        .iter()
+        .filter_map(SearchResultItem::identity)
+        .filter(|found| accept(found))
+        .find_map(|found| found.ids.imdb)
+}
+
+/// `cache stats` lists the cached entries per kind, `cache purge [--expired]
+/// [kind]` removes them.
+fn run_cache_command(path: &str, args: &[String]) {
+    let cache = match MetadataCache::open(path) {
+        Ok(cache) => cache,
+        Err(e) => {
+            eprintln!("Cannot open cache {path}: {e}");
+            return;
+        }
+    };
+    match args.first().map(String::as_str) {
+        Some("stats") => match cache.stats() {
+            Ok(stats) => {
+                for stat in stats {
+                    println!(
+                        "{:<12} {:>8} entries, {:>8} expired",
+                        stat.kind.as_str(),
+                        stat.entries,
+                        stat.expired
+                    );
+                }
+            }
+            Err(e) => eprintln!("Cannot read cache {path}: {e}"),
+        },
+        Some("purge") => {
+            let expired_only = args.iter().any(|arg| arg == "--expired");
+            let kind = match args[1..].iter().find(|arg| !arg.starts_with("--")) {
+                Some(name) => match CacheKind::parse(name) {
+                    Some(kind) => Some(kind),
+                    None => {
+                        eprintln!("Unknown cache kind {name}");
+                        return;
+                    }
+                },
+                None => None,
             };
-            let imdb = imdb.as_ref().unwrap();
-            println!("Got imdb result {:?}", imdb);
+            match cache.purge(kind, expired_only) {
+                Ok(removed) => println!("Removed {removed} entries"),
+                Err(e) => eprintln!("Cannot purge cache {path}: {e}"),
+            }
+        }
+        _ => eprintln!(
+            "usage: jell-debrid cache stats | purge [--expired] [id-mapping|not-found|metadata]"
+        ),
+    }
+}
+
+/// The client, or `None` after logging why it could not be built, e.g. an
+/// API key with characters not allowed in a header.
+fn configured<T>(service: &str, client: Result<T, Error>) -> Option<T> {
+    match client {
+        Ok(client) => Some(client),
+        Err(e) => {
+            log::error!("Cannot set up the {service} client: {e}");
+            None
+        }
+    }
+}
+
+/// Runs the Trakt device flow and stores the resulting token. Ctrl-C aborts
+/// the wait for approval.
+async fn authorize_trakt(
+    credentials: &TraktCredentials,
+    store: &TokenStore,
+) -> Result<TraktOAuthToken, DeviceAuthError> {
+    let code = TraktClient::start_device_auth(&credentials.client_id).await?;
+    println!(
+        "Open this URL in your browser:\n{}\nand enter the code: {}",
+        code.verification_url, code.user_code,
+    );
+
+    let cancel = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    let token = TraktClient::poll_device_token(
+        &credentials.client_id,
+        &credentials.client_secret,
+        &code,
+        cancel,
+    )
+    .await?;
+    if let Err(e) = store.save(&token) {
+        log::error!("Failed to store Trakt token: {e}");
+    }
+    Ok(token)
+}
+
+/// Refreshes and stores the Trakt token if it is about to expire. Returns the
+/// new token, or `None` if the current one is still valid or refreshing failed.
+pub(crate) async fn refresh_trakt_token(
+    credentials: &TraktCredentials,
+    store: &TokenStore,
+    token: &TraktOAuthToken,
+) -> Option<TraktOAuthToken> {
+    if !token.expires_within(TRAKT_REFRESH_MARGIN) {
+        return None;
+    }
+    let refreshed = match TraktClient::refresh_token(
+        &credentials.client_id,
+        &credentials.client_secret,
+        &token.refresh_token,
+    )
+    .await
+    {
+        Ok(refreshed) => refreshed,
+        Err(e) => {
+            report_error("Trakt", &e);
+            return None;
+        }
+    };
+    log::info!("Refreshed Trakt access token");
+    if let Err(e) = store.save(&refreshed) {
+        log::error!("Failed to store Trakt token: {e}");
+    }
+    Some(refreshed)
+}
+
+/// Logs a client error, distinguishing an unreachable service from one whose
+/// responses no longer match our models.
+pub(crate) fn report_error(service: &str, error: &Error) {
+    match error {
+        Error::Transport { .. } => log::warn!("{service} is unreachable: {error}"),
+        Error::Decode { text, .. } => {
+            log::error!("{service} returned an unexpected payload: {error}: {text}")
+        }
+        Error::Auth { .. } => log::error!("{service} rejected our credentials: {error}"),
+        Error::Status { body, .. } => {
+            let message = error.provider_message().unwrap_or(body);
+            log::error!("{service} request failed: {error}: {message}")
         }
+        _ => log::error!("{service} request failed: {error}"),
     }
 }
@@ -0,0 +1,148 @@
+use clients::seerrs::client::SeerrClient;
+use clients::seerrs::structs::{MediaRequest, RequestStatus, User, UserType};
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::IdLookup;
+
+/// Decides on Seerr requests waiting for approval: approves those of trusted
+/// users and declines those for titles that cannot be found.
+pub struct SeerrModerator {
+    /// Seerr user ids, or Jellyfin or Plex usernames, whose requests are
+    /// approved.
+    trusted: Vec<String>,
+    decline_unresolvable: bool,
+}
+
+impl SeerrModerator {
+    pub fn new(trusted: &str, decline_unresolvable: bool) -> Self {
+        Self {
+            trusted: trusted
+                .split(',')
+                .map(|user| user.trim().to_lowercase())
+                .filter(|user| !user.is_empty())
+                .collect(),
+            decline_unresolvable,
+        }
+    }
+
+    /// Whether there is anything to decide on at all.
+    pub fn is_enabled(&self) -> bool {
+        !self.trusted.is_empty() || self.decline_unresolvable
+    }
+
+    pub async fn moderate(&self, seerr: &SeerrClient, trakt: &TraktClient) {
+        let pending = match seerr.get_pending_requests().await {
+            Ok(pending) => pending,
+            Err(e) => {
+                crate::report_error("Seerr", &e);
+                return;
+            }
+        };
+        for request in pending {
+            if request.status != RequestStatus::PendingApproval {
+                continue;
+            }
+            let decision = if self.decline_unresolvable && !exists(trakt, &request).await {
+                log::info!(
+                    "Declining Seerr request {} for unknown TMDB id {}",
+                    request.id,
+                    request.media.tmdb_id
+                );
+                seerr.decline_request(request.id).await
+            } else if self.is_trusted(&request.requested_by) {
+                log::info!(
+                    "Approving Seerr request {} by {}",
+                    request.id,
+                    request.requested_by.display_name
+                );
+                seerr.approve_request(request.id).await
+            } else {
+                continue;
+            };
+            if let Err(e) = decision {
+                crate::report_error("Seerr", &e);
+            }
+        }
+    }
+
+    /// Only names issued by the media server count: Seerr lets users edit
+    /// their own display name and username, so those prove nothing.
+    fn is_trusted(&self, user: &User) -> bool {
+        let name = match user.user_type {
+            UserType::Jellyfin | UserType::Emby => user.jellyfin_username.as_deref(),
+            UserType::Plex => user.plex_username.as_deref(),
+            _ => None,
+        };
+        let id = user.id.to_string();
+        self.trusted.iter().any(|trusted| {
+            *trusted == id || name.is_some_and(|name| name.eq_ignore_ascii_case(trusted))
+        })
+    }
+}
+
+/// Whether Trakt knows the requested title. Failed lookups count as known so
+/// an outage never declines anything.
+async fn exists(trakt: &TraktClient, request: &MediaRequest) -> bool {
+    let Some(media_type) = request.r#type.as_media_type() else {
+        return true;
+    };
+    match trakt
+        .search(&IdLookup::Tmdb(request.media.tmdb_id), Some(media_type))
+        .await
+    {
+        Ok(search) => !search.searchresult.is_empty(),
+        Err(e) => {
+            crate::report_error("Trakt", &e);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(json: &str) -> User {
+        let mut user: serde_json::Value = serde_json::json!({
+            "id": 7,
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "updatedAt": "2024-01-01T00:00:00.000Z",
+            "displayName": "mallory",
+            "permissions": 32,
+            "userType": 2,
+        });
+        let overrides: serde_json::Value = serde_json::from_str(json).unwrap();
+        for (key, value) in overrides.as_object().unwrap() {
+            user[key] = value.clone();
+        }
+        serde_json::from_value(user).unwrap()
+    }
+
+    #[test]
+    fn trusts_ids_and_media_server_names() {
+        let moderator = SeerrModerator::new("alice, 7", false);
+        assert!(moderator.is_trusted(&user("{}")));
+
+        let moderator = SeerrModerator::new("Alice", false);
+        assert!(moderator.is_trusted(&user(
+            r#"{"id": 3, "userType": 3, "jellyfinUsername": "alice"}"#
+        )));
+        assert!(moderator.is_trusted(&user(
+            r#"{"id": 3, "userType": 1, "plexUsername": "alice"}"#
+        )));
+    }
+
+    #[test]
+    fn copied_display_name_is_not_trusted() {
+        let moderator = SeerrModerator::new("alice", false);
+        let impostor = user(
+            r#"{"displayName": "alice", "username": "alice", "email": "alice", "userType": 2}"#,
+        );
+        assert!(!moderator.is_trusted(&impostor));
+
+        // A Plex name on a Jellyfin account is not one Jellyfin issued.
+        let impostor = user(
+            r#"{"displayName": "alice", "userType": 3, "jellyfinUsername": "mallory", "plexUsername": "alice"}"#,
+        );
+        assert!(!moderator.is_trusted(&impostor));
+    }
+}
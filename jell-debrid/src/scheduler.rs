@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+
+use clients::media::MediaIds;
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::CalendarShow;
+use clients::Error;
+
+use crate::sources::AcquisitionRequest;
+
+/// How many days of the calendar, starting yesterday, are kept in view.
+const CALENDAR_DAYS: u32 = 8;
+
+/// Follows the shows on the user's Trakt calendar and releases each new
+/// episode as an acquisition request once it has aired.
+pub struct EpisodeScheduler {
+    /// How long after an episode airs to wait before acquiring it, giving
+    /// releases time to appear.
+    delay: Duration,
+    /// How often the calendar is fetched again.
+    refresh_every: Duration,
+    last_refresh: Option<DateTime<Utc>>,
+    upcoming: Vec<CalendarShow>,
+    /// Episodes already released.
+    released: HashSet<EpisodeKey>,
+}
+
+impl EpisodeScheduler {
+    pub fn new(delay: Duration, refresh_every: Duration) -> Self {
+        Self {
+            delay,
+            refresh_every,
+            last_refresh: None,
+            upcoming: Vec::new(),
+            released: HashSet::new(),
+        }
+    }
+
+    /// Fetches the calendar again if it is older than `refresh_every`.
+    pub async fn refresh(&mut self, trakt: &TraktClient, now: DateTime<Utc>) -> Result<(), Error> {
+        if self
+            .last_refresh
+            .is_some_and(|last| now - last < self.refresh_every)
+        {
+            return Ok(());
+        }
+        let start = (now - Duration::days(1)).date_naive();
+        let upcoming = trakt.get_my_calendar_shows(start, CALENDAR_DAYS).await?;
+        log::debug!(
+            "Following {} episodes on the Trakt calendar",
+            upcoming.len()
+        );
+
+        let keys: HashSet<EpisodeKey> = upcoming.iter().map(EpisodeKey::of).collect();
+        self.released.retain(|key| keys.contains(key));
+        self.upcoming = upcoming;
+        self.last_refresh = Some(now);
+        Ok(())
+    }
+
+    /// Episodes that aired at least `delay` ago and were not released yet.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<AcquisitionRequest> {
+        let mut due = Vec::new();
+        for airing in &self.upcoming {
+            if airing.first_aired + self.delay > now {
+                continue;
+            }
+            if !self.released.insert(EpisodeKey::of(airing)) {
+                continue;
+            }
+            log::info!(
+                "{} S{:02}E{:02} aired at {}",
+                airing.show.title,
+                airing.episode.season,
+                airing.episode.number,
+                airing.first_aired
+            );
+            due.push(AcquisitionRequest {
+                media: airing.identity(),
+                seasons: Vec::new(),
+                sources: vec!["trakt-calendar"],
+            });
+        }
+        due
+    }
+}
+
+/// Identifies an episode by its Trakt id, or by its show and numbers when
+/// Trakt did not send one.
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum EpisodeKey {
+    Trakt(u64),
+    Numbered {
+        show: MediaIds,
+        season: u32,
+        number: u32,
+    },
+}
+
+impl EpisodeKey {
+    fn of(airing: &CalendarShow) -> Self {
+        match airing.episode.ids.trakt {
+            Some(id) => EpisodeKey::Trakt(id),
+            None => EpisodeKey::Numbered {
+                show: MediaIds::from(&airing.show.ids),
+                season: airing.episode.season,
+                number: airing.episode.number,
+            },
+        }
+    }
+}
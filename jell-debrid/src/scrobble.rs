@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use clients::jellyfin::client::JellyfinClient;
+use clients::jellyfin::structs::{BaseItem, SessionInfo};
+use clients::media::MediaIds;
+use clients::trakt::auth::{TokenStore, TraktOAuthToken};
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::{IDs, Scrobble};
+use clients::Error;
+
+use crate::TraktCredentials;
+
+/// Sessions idle for longer than this are not reported by Jellyfin.
+const ACTIVE_WITHIN_SECONDS: u64 = 960;
+
+/// A Jellyfin user whose playback is scrobbled to their own Trakt account.
+pub struct ScrobbleUser {
+    jellyfin_user: String,
+    store: TokenStore,
+    token: TraktOAuthToken,
+    trakt: TraktClient,
+}
+
+impl ScrobbleUser {
+    /// Parses a comma-separated `jellyfin_user=token_file` mapping. Users
+    /// whose token file cannot be loaded are skipped.
+    pub fn load_all(spec: &str, client_id: &str) -> Vec<ScrobbleUser> {
+        let mut users = Vec::new();
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((user, path)) = entry.split_once('=') else {
+                log::warn!("Ignoring scrobble user {entry}, expected user=token_file");
+                continue;
+            };
+            let store = TokenStore::new(path.trim());
+            let token = match store.load() {
+                Ok(Some(token)) => token,
+                Ok(None) => {
+                    log::warn!("No Trakt token for {user} at {path}, not scrobbling");
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Not scrobbling for {user}: {e}");
+                    continue;
+                }
+            };
+            let trakt = match TraktClient::new(&token.access_token, client_id) {
+                Ok(trakt) => trakt,
+                Err(e) => {
+                    log::warn!("Not scrobbling for {user}: {e}");
+                    continue;
+                }
+            };
+            users.push(ScrobbleUser {
+                jellyfin_user: user.trim().to_string(),
+                trakt,
+                store,
+                token,
+            });
+        }
+        users
+    }
+}
+
+/// What a session was last seen playing.
+struct Playback {
+    user: usize,
+    item_id: String,
+    scrobble: Scrobble,
+    paused: bool,
+}
+
+/// Polls Jellyfin sessions and reports starts, pauses and stops of mapped
+/// users to Trakt.
+pub struct Scrobbler {
+    jellyfin: JellyfinClient,
+    credentials: TraktCredentials,
+    users: Vec<ScrobbleUser>,
+    /// Playback per Jellyfin session id.
+    playing: HashMap<String, Playback>,
+    /// External ids of series, by Jellyfin id.
+    series: HashMap<String, MediaIds>,
+}
+
+impl Scrobbler {
+    pub fn new(
+        jellyfin: JellyfinClient,
+        credentials: TraktCredentials,
+        users: Vec<ScrobbleUser>,
+    ) -> Self {
+        Self {
+            jellyfin,
+            credentials,
+            users,
+            playing: HashMap::new(),
+            series: HashMap::new(),
+        }
+    }
+
+    pub async fn poll(&mut self) {
+        for user in &mut self.users {
+            if let Some(refreshed) =
+                crate::refresh_trakt_token(&self.credentials, &user.store, &user.token).await
+            {
+                match user.trakt.set_access_token(&refreshed.access_token) {
+                    Ok(()) => user.token = refreshed,
+                    Err(e) => crate::report_error("Trakt", &e),
+                }
+            }
+        }
+
+        let sessions = match self.jellyfin.get_sessions(ACTIVE_WITHIN_SECONDS).await {
+            Ok(sessions) => sessions,
+            Err(e) => {
+                crate::report_error("Jellyfin", &e);
+                return;
+            }
+        };
+
+        let mut still_playing = Vec::new();
+        for session in &sessions {
+            let Some(user) = self.user_index(session) else {
+                continue;
+            };
+            let (Some(item), Some(state)) = (&session.now_playing_item, &session.play_state) else {
+                continue;
+            };
+            still_playing.push(session.id.clone());
+
+            let progress = progress(item, state.position_ticks);
+            let previous = self.playing.remove(&session.id);
+            let changed_item = previous
+                .as_ref()
+                .is_some_and(|p| p.item_id != item.id || p.user != user);
+            if changed_item {
+                self.stop(previous.unwrap()).await;
+            } else if let Some(mut playback) = previous {
+                playback.scrobble.progress = progress;
+                if playback.paused != state.is_paused {
+                    playback.paused = state.is_paused;
+                    self.send(&playback).await;
+                }
+                self.playing.insert(session.id.clone(), playback);
+                continue;
+            }
+
+            let Some(scrobble) = self.scrobble_for(item, progress).await else {
+                continue;
+            };
+            let playback = Playback {
+                user,
+                item_id: item.id.clone(),
+                scrobble,
+                paused: state.is_paused,
+            };
+            self.send(&playback).await;
+            self.playing.insert(session.id.clone(), playback);
+        }
+
+        let ended: Vec<String> = self
+            .playing
+            .keys()
+            .filter(|id| !still_playing.contains(id))
+            .cloned()
+            .collect();
+        for id in ended {
+            if let Some(playback) = self.playing.remove(&id) {
+                self.stop(playback).await;
+            }
+        }
+    }
+
+    fn user_index(&self, session: &SessionInfo) -> Option<usize> {
+        let name = session.user_name.as_deref()?;
+        self.users
+            .iter()
+            .position(|user| user.jellyfin_user.eq_ignore_ascii_case(name))
+    }
+
+    /// Identifies a movie by its own ids and an episode through its series,
+    /// falling back to the episode's ids when the series has none.
+    async fn scrobble_for(&mut self, item: &BaseItem, progress: f64) -> Option<Scrobble> {
+        match item.r#type.as_str() {
+            "Movie" => Some(Scrobble::movie(IDs::from(&item.media_ids()), progress)),
+            "Episode" => {
+                let series = match &item.series_id {
+                    Some(id) => self.series_ids(id).await,
+                    None => None,
+                };
+                match (series, item.parent_index_number, item.index_number) {
+                    (Some(series), Some(season), Some(number)) => Some(Scrobble::episode(
+                        IDs::from(&series),
+                        season,
+                        number,
+                        progress,
+                    )),
+                    _ => Some(Scrobble::episode_ids(
+                        IDs::from(&item.media_ids()),
+                        progress,
+                    )),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    async fn series_ids(&mut self, id: &str) -> Option<MediaIds> {
+        if let Some(ids) = self.series.get(id) {
+            return Some(ids.clone());
+        }
+        let series = match self.jellyfin.get_item(id).await {
+            Ok(series) => series,
+            Err(e) => {
+                crate::report_error("Jellyfin", &e);
+                return None;
+            }
+        };
+        let ids = series.media_ids();
+        if ids == MediaIds::default() {
+            return None;
+        }
+        self.series.insert(id.to_string(), ids.clone());
+        Some(ids)
+    }
+
+    /// Reports the current state of a playback.
+    async fn send(&self, playback: &Playback) {
+        let trakt = &self.users[playback.user].trakt;
+        let result = if playback.paused {
+            trakt.scrobble_pause(&playback.scrobble).await
+        } else {
+            trakt.scrobble_start(&playback.scrobble).await
+        };
+        log_result(&self.users[playback.user], result);
+    }
+
+    async fn stop(&self, playback: Playback) {
+        let user = &self.users[playback.user];
+        log_result(user, user.trakt.scrobble_stop(&playback.scrobble).await);
+    }
+}
+
+fn log_result<T: std::fmt::Debug>(user: &ScrobbleUser, result: Result<T, Error>) {
+    match result {
+        Ok(response) => log::debug!("Scrobbled for {}: {response:?}", user.jellyfin_user),
+        // Trakt refuses scrobbling the same item twice within a few minutes.
+        Err(e) if e.status().is_some_and(|status| status.as_u16() == 409) => {
+            log::debug!("{} was already scrobbled", user.jellyfin_user)
+        }
+        Err(e) => crate::report_error("Trakt scrobble", &e),
+    }
+}
+
+/// Playback progress in percent.
+fn progress(item: &BaseItem, position_ticks: Option<u64>) -> f64 {
+    match (position_ticks, item.run_time_ticks) {
+        (Some(position), Some(runtime)) if runtime > 0 => {
+            (position as f64 / runtime as f64 * 100.0).clamp(0.0, 100.0)
+        }
+        _ => 0.0,
+    }
+}
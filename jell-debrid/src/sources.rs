@@ -0,0 +1,269 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+
+use clients::media::{MediaIdentity, MediaIds};
+use clients::seerrs::client::SeerrClient;
+use clients::seerrs::structs::MediaRequest;
+use clients::trakt::client::TraktClient;
+use clients::trakt::structs::{SortOrder, WatchListSort};
+use clients::Error;
+
+/// Something somebody asked to have acquired.
+#[derive(Debug, Clone)]
+pub struct AcquisitionRequest {
+    pub media: MediaIdentity,
+    /// Seasons asked for when `media` is a whole show. Empty means all.
+    pub seasons: Vec<u32>,
+    /// Names of the sources that asked for it.
+    pub sources: Vec<&'static str>,
+}
+
+/// A place acquisition requests come from.
+#[async_trait(?Send)]
+pub trait RequestSource {
+    fn name(&self) -> &'static str;
+
+    async fn fetch(&self) -> Result<Vec<AcquisitionRequest>, Error>;
+}
+
+/// The Seerr requests fetched by the last `SeerrSource::fetch`, kept for the
+/// steps later in the same pass so they need not page through Seerr again.
+pub type FetchedSeerrRequests = Rc<Cell<Option<Vec<MediaRequest>>>>;
+
+/// Requests still being processed in Seerr.
+pub struct SeerrSource<'a> {
+    client: &'a SeerrClient,
+    fetched: FetchedSeerrRequests,
+}
+
+impl<'a> SeerrSource<'a> {
+    pub fn new(client: &'a SeerrClient, fetched: FetchedSeerrRequests) -> Self {
+        Self { client, fetched }
+    }
+}
+
+#[async_trait(?Send)]
+impl RequestSource for SeerrSource<'_> {
+    fn name(&self) -> &'static str {
+        "seerr"
+    }
+
+    async fn fetch(&self) -> Result<Vec<AcquisitionRequest>, Error> {
+        let requests = self.client.get_unfulfilled_requests().await?;
+        let acquisitions = requests
+            .iter()
+            .filter_map(|request| {
+                let Some(media_type) = request.r#type.as_media_type() else {
+                    log::warn!(
+                        "Skipping Seerr request {} of type {:?}",
+                        request.id,
+                        request.r#type
+                    );
+                    return None;
+                };
+                let ids = MediaIds {
+                    tmdb: Some(request.media.tmdb_id),
+                    tvdb: request.media.tvdb_id,
+                    ..MediaIds::default()
+                };
+                Some(AcquisitionRequest {
+                    media: MediaIdentity::new(media_type, ids),
+                    seasons: request
+                        .seasons
+                        .iter()
+                        .map(|season| season.season_number)
+                        .collect(),
+                    sources: vec![self.name()],
+                })
+            })
+            .collect();
+        self.fetched.set(Some(requests));
+        Ok(acquisitions)
+    }
+}
+
+/// Everything on the authenticated user's Trakt watchlist.
+pub struct TraktWatchlistSource<'a> {
+    client: &'a TraktClient,
+}
+
+impl<'a> TraktWatchlistSource<'a> {
+    pub fn new(client: &'a TraktClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait(?Send)]
+impl RequestSource for TraktWatchlistSource<'_> {
+    fn name(&self) -> &'static str {
+        "trakt-watchlist"
+    }
+
+    async fn fetch(&self) -> Result<Vec<AcquisitionRequest>, Error> {
+        self.client
+            .watchlist(&[], WatchListSort::Rank, SortOrder::Asc)
+            .map_ok(|item| AcquisitionRequest {
+                media: item.identity(),
+                seasons: Vec::new(),
+                sources: vec![self.name()],
+            })
+            .try_collect()
+            .await
+    }
+}
+
+/// A Trakt list the daemon follows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListSubscription {
+    /// A custom list, given as `user/list` where `list` is a slug or id.
+    Custom { user: String, list: String },
+    /// Every list the authenticated user has liked, given as `liked`.
+    Liked,
+}
+
+impl ListSubscription {
+    /// Parses a comma-separated set of subscriptions, skipping invalid ones.
+    pub fn parse_all(spec: &str) -> Vec<ListSubscription> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .filter_map(|entry| {
+                if entry == "liked" {
+                    return Some(ListSubscription::Liked);
+                }
+                match entry.split_once('/') {
+                    Some((user, list)) if !user.is_empty() && !list.is_empty() => {
+                        Some(ListSubscription::Custom {
+                            user: user.to_string(),
+                            list: list.to_string(),
+                        })
+                    }
+                    _ => {
+                        log::warn!("Ignoring invalid Trakt list {entry}, expected user/list");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// Every movie and show on a set of followed Trakt lists.
+pub struct TraktListSource<'a> {
+    client: &'a TraktClient,
+    lists: Vec<ListSubscription>,
+}
+
+impl<'a> TraktListSource<'a> {
+    pub fn new(client: &'a TraktClient, lists: Vec<ListSubscription>) -> Self {
+        Self { client, lists }
+    }
+
+    /// The `(user, list)` pairs to fetch. If the liked lists cannot be
+    /// fetched the custom ones are still returned.
+    async fn custom_lists(&self) -> Vec<(String, String)> {
+        let mut lists = Vec::new();
+        for subscription in &self.lists {
+            match subscription {
+                ListSubscription::Custom { user, list } => lists.push((user.clone(), list.clone())),
+                ListSubscription::Liked => {
+                    let liked = match self.client.get_liked_lists().await {
+                        Ok(liked) => liked,
+                        Err(e) => {
+                            crate::report_error("Trakt liked lists", &e);
+                            continue;
+                        }
+                    };
+                    lists.extend(liked.into_iter().filter_map(|liked| {
+                        let user = liked.list.user?.ids.slug;
+                        Some((user, liked.list.ids.trakt.to_string()))
+                    }));
+                }
+            }
+        }
+        lists
+    }
+}
+
+#[async_trait(?Send)]
+impl RequestSource for TraktListSource<'_> {
+    fn name(&self) -> &'static str {
+        "trakt-lists"
+    }
+
+    /// A list that fails is logged and skipped so the others still count.
+    async fn fetch(&self) -> Result<Vec<AcquisitionRequest>, Error> {
+        let mut requests = Vec::new();
+        for (user, list) in self.custom_lists().await {
+            let items = match self.client.get_list_items(&user, &list).await {
+                Ok(items) => items,
+                Err(e) => {
+                    crate::report_error(&format!("Trakt list {user}/{list}"), &e);
+                    continue;
+                }
+            };
+            requests.extend(items.iter().filter_map(|item| {
+                Some(AcquisitionRequest {
+                    media: item.identity()?,
+                    seasons: Vec::new(),
+                    sources: vec![self.name()],
+                })
+            }));
+        }
+        Ok(requests)
+    }
+}
+
+/// Fetches every source and merges requests for the same media. A source that
+/// fails is logged and skipped so the others still get processed.
+pub async fn collect_requests(sources: &[Box<dyn RequestSource + '_>]) -> Vec<AcquisitionRequest> {
+    let mut merged: Vec<AcquisitionRequest> = Vec::new();
+    for source in sources {
+        let requests = match source.fetch().await {
+            Ok(requests) => requests,
+            Err(e) => {
+                crate::report_error(source.name(), &e);
+                continue;
+            }
+        };
+        for request in requests {
+            push_merged(&mut merged, request);
+        }
+    }
+    merged
+}
+
+/// Adds `request` to `requests`, folding it into an existing request for the
+/// same media if there is one.
+pub fn push_merged(requests: &mut Vec<AcquisitionRequest>, request: AcquisitionRequest) {
+    match requests
+        .iter_mut()
+        .find(|existing| existing.media.is_same(&request.media))
+    {
+        Some(existing) => merge(existing, request),
+        None => requests.push(request),
+    }
+}
+
+fn merge(existing: &mut AcquisitionRequest, other: AcquisitionRequest) {
+    existing.media.ids.merge(&other.media.ids);
+    existing.media.title = existing.media.title.take().or(other.media.title);
+    existing.media.year = existing.media.year.or(other.media.year);
+    if existing.seasons.is_empty() || other.seasons.is_empty() {
+        existing.seasons.clear();
+    } else {
+        for season in other.seasons {
+            if !existing.seasons.contains(&season) {
+                existing.seasons.push(season);
+            }
+        }
+    }
+    for source in other.sources {
+        if !existing.sources.contains(&source) {
+            existing.sources.push(source);
+        }
+    }
+}